@@ -2,6 +2,7 @@ use std::error::Error;
 
 use actor::{SGroups, SUserType};
 use bon::Builder;
+use capctl::CapSet;
 use chrono::Duration;
 use linked_hash_set::LinkedHashSet;
 use options::EnvBehavior;
@@ -30,6 +31,29 @@ pub struct FilterMatcher {
     pub user: Option<u32>,
     #[builder(with = |s: impl Into<SGroups>| -> Result<_,String> { s.into().try_into() })]
     pub group: Option<Vec<u32>>,
+    /// Capabilities requested with `dosr --caps`, the task must grant all of them
+    pub caps: Option<CapSet>,
+    /// Capabilities removed with `dosr --drop-caps`
+    pub drop_caps: Option<CapSet>,
+}
+
+impl FilterMatcher {
+    pub fn grants_caps(&self, caps: Option<CapSet>) -> bool {
+        self.caps
+            .is_none_or(|requested| caps.unwrap_or_default() & requested == requested)
+    }
+
+    /// Only the requested capabilities of the task are given to the command
+    pub fn narrow_caps(&self, caps: Option<CapSet>) -> Option<CapSet> {
+        let caps = match self.caps {
+            Some(requested) => caps.map(|caps| caps & requested),
+            None => caps,
+        };
+        match self.drop_caps {
+            Some(dropped) => caps.map(|caps| caps & !dropped),
+            None => caps,
+        }
+    }
 }
 
 // deserialize the linked hash set
@@ -136,6 +160,27 @@ fn convert_string_to_duration(s: &str) -> Result<Option<chrono::TimeDelta>, Box<
 #[cfg(test)]
 mod tests {
     use super::*;
+    use capctl::Cap;
+
+    #[test]
+    fn test_filter_matcher_caps() {
+        let granted = CapSet::from_iter([Cap::NET_BIND_SERVICE, Cap::NET_RAW, Cap::SYS_ADMIN]);
+        let filter = FilterMatcher::builder().build();
+        assert!(filter.grants_caps(None));
+        assert_eq!(filter.narrow_caps(Some(granted)), Some(granted));
+        let filter = FilterMatcher::builder()
+            .caps(CapSet::from_iter([Cap::NET_BIND_SERVICE, Cap::NET_RAW]))
+            .drop_caps(CapSet::from_iter([Cap::NET_RAW]))
+            .build();
+        assert!(filter.grants_caps(Some(granted)));
+        assert!(!filter.grants_caps(Some(CapSet::from_iter([Cap::NET_RAW]))));
+        assert!(!filter.grants_caps(None));
+        assert_eq!(
+            filter.narrow_caps(Some(granted)),
+            Some(CapSet::from_iter([Cap::NET_BIND_SERVICE]))
+        );
+        assert_eq!(filter.narrow_caps(None), None);
+    }
 
     struct LinkedHashSetTester<T>(pub Option<LinkedHashSet<T>>);
 
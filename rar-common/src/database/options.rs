@@ -1,11 +1,12 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::num::ParseIntError;
 use std::result::Result;
 use std::str::FromStr;
 use std::{borrow::Borrow, cell::RefCell, rc::Rc};
 
 use bon::{bon, Builder};
+use capctl::prctl::Secbits;
 use chrono::Duration;
 
 use konst::eq_str;
@@ -25,12 +26,13 @@ use crate::util::{
     AUTHENTICATION, BOUNDING, ENV_CHECK_LIST, ENV_DEFAULT_BEHAVIOR, ENV_DELETE_LIST, ENV_KEEP_LIST,
     ENV_OVERRIDE_BEHAVIOR, ENV_PATH_ADD_LIST_SLICE, ENV_PATH_BEHAVIOR, ENV_PATH_REMOVE_LIST_SLICE,
     ENV_SET_LIST, HARDENED_ENUM_VALUE_0, HARDENED_ENUM_VALUE_1, HARDENED_ENUM_VALUE_2,
-    HARDENED_ENUM_VALUE_3, INFO, PRIVILEGED, TIMEOUT_DURATION, TIMEOUT_TYPE, UMASK,
+    HARDENED_ENUM_VALUE_3, INFO, PRIVILEGED, PTY, RECORD, TIMEOUT_DURATION, TIMEOUT_TYPE, UMASK,
 };
 
 use super::{deserialize_duration, is_default, serialize_duration, FilterMatcher};
 
 use super::{
+    actor::SUserType,
     lhs_deserialize, lhs_deserialize_envkey, lhs_serialize, lhs_serialize_envkey,
     structs::{SConfig, SRole, STask},
 };
@@ -56,6 +58,20 @@ pub enum OptType {
     Authentication,
     ExecInfo,
     UMask,
+    Pty,
+    Record,
+    Cwd,
+    Chroot,
+    RLimits,
+    Cgroup,
+    Sandbox,
+    NoExec,
+    Intercept,
+    SecureBits,
+    NoNewPrivs,
+    CapabilitiesMode,
+    MaxRuntime,
+    Askpass,
 }
 
 #[derive(
@@ -261,6 +277,109 @@ impl Default for SAuthentication {
     }
 }
 
+/// Account whose password is asked
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default, EnumIs)]
+#[serde(rename_all = "kebab-case")]
+pub enum SAuthUser {
+    /// The user running dosr
+    #[default]
+    Caller,
+    /// The user the command runs as
+    Target,
+    /// A designated account, e.g. root
+    Account(SUserType),
+}
+
+/// Authentication of the user before the command. The string form only sets the `mode`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default, Builder)]
+#[serde(rename_all = "kebab-case")]
+pub struct SAuthPolicy {
+    #[serde(default)]
+    #[builder(default)]
+    pub mode: SAuthentication,
+    /// Account authenticated, the caller by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<SAuthUser>,
+    /// PAM service, instead of the service dosr was built with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub service: Option<String>,
+    /// Password prompt, `dosr --prompt` takes precedence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub prompt: Option<String>,
+    /// Attempts given after a wrong password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u8>,
+    /// Time given to type the password, in HH:MM:SS
+    #[serde(
+        default,
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub prompt_timeout: Option<Duration>,
+    /// Message shown to each user before their first authentication
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub lecture: Option<String>,
+}
+
+impl From<SAuthentication> for SAuthPolicy {
+    fn from(mode: SAuthentication) -> Self {
+        SAuthPolicy::builder().mode(mode).build()
+    }
+}
+
+/// A policy with only a mode is written as the mode
+pub fn serialize_authentication<S>(
+    value: &Option<SAuthPolicy>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match value {
+        Some(policy) if *policy == SAuthPolicy::from(policy.mode) => {
+            policy.mode.serialize(serializer)
+        }
+        Some(policy) => policy.serialize(serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn deserialize_authentication<'de, D>(deserializer: D) -> Result<Option<SAuthPolicy>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawAuthentication {
+        Mode(SAuthentication),
+        Policy(SAuthPolicy),
+    }
+    Ok(Some(match RawAuthentication::deserialize(deserializer)? {
+        RawAuthentication::Mode(mode) => mode.into(),
+        RawAuthentication::Policy(policy) => policy,
+    }))
+}
+
+/// A relative path would depend on the working directory of the caller
+pub fn deserialize_absolute_path<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + AsRef<str>,
+{
+    let path = Option::<T>::deserialize(deserializer)?;
+    match &path {
+        Some(p) if !p.as_ref().starts_with('/') => Err(serde::de::Error::custom(format!(
+            "{} is not an absolute path",
+            p.as_ref()
+        ))),
+        _ => Ok(path),
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct SUMask(
@@ -333,6 +452,428 @@ pub enum SInfo {
     Show = HARDENED_ENUM_VALUE_1,
 }
 
+#[derive(
+    Serialize, Deserialize, PartialEq, Eq, Debug, EnumIs, Display, Clone, Copy, EnumString,
+)]
+#[strum(ascii_case_insensitive)]
+#[serde(rename_all = "kebab-case")]
+#[repr(u32)]
+pub enum SPty {
+    /// Allocate a pseudo-terminal only when both stdin and stdout are terminals
+    Auto = HARDENED_ENUM_VALUE_0,
+    Always = HARDENED_ENUM_VALUE_1,
+    Never = HARDENED_ENUM_VALUE_2,
+}
+
+impl Default for SPty {
+    fn default() -> Self {
+        PTY
+    }
+}
+
+/// Session recording format, recorded sessions can be replayed with `dosr --replay <id>`
+#[derive(
+    Serialize, Deserialize, PartialEq, Eq, Debug, EnumIs, Display, Clone, Copy, EnumString,
+)]
+#[strum(ascii_case_insensitive)]
+#[serde(rename_all = "kebab-case")]
+#[repr(u32)]
+pub enum SRecord {
+    Disabled = HARDENED_ENUM_VALUE_0,
+    Asciicast = HARDENED_ENUM_VALUE_1,
+    Ttyrec = HARDENED_ENUM_VALUE_2,
+}
+
+impl Default for SRecord {
+    fn default() -> Self {
+        RECORD
+    }
+}
+
+/// How the capabilities of the task are delivered to the command
+#[derive(
+    Serialize, Deserialize, PartialEq, Eq, Debug, EnumIs, Display, Clone, Copy, EnumString,
+)]
+#[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+#[derive(Default)]
+#[repr(u32)]
+pub enum SCapabilitiesMode {
+    /// Raised in the ambient set, kept by the programs executed by the command
+    #[default]
+    Ambient = HARDENED_ENUM_VALUE_0,
+    /// Only in the inheritable set, for binaries with inheritable file capabilities
+    Inheritable = HARDENED_ENUM_VALUE_1,
+    /// Rejected by dosr and chsr: Linux keeps the ambient capabilities across every exec of a
+    /// binary without file capabilities, only the command itself could clear them after its exec
+    AmbientNoPropagate = HARDENED_ENUM_VALUE_2,
+}
+
+impl SCapabilitiesMode {
+    /// Why this mode cannot be enforced, if it cannot
+    pub fn unsupported(&self) -> Option<&'static str> {
+        match self {
+            SCapabilitiesMode::AmbientNoPropagate => Some(
+                "capabilities-mode ambient-no-propagate is not supported: Linux cannot limit ambient capabilities to the first exec",
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// Working directory of the command: an absolute path, `~` being the target user home,
+/// or `*` to let the user choose it with `dosr --chdir`
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub enum SCwd {
+    Any,
+    Path(String),
+}
+
+impl FromStr for SCwd {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "*" => Ok(SCwd::Any),
+            _ if s == "~" || s.starts_with("~/") || s.starts_with('/') => {
+                Ok(SCwd::Path(s.to_string()))
+            }
+            _ => Err(format!(
+                "invalid working directory {}, expected an absolute path, ~ or *",
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for SCwd {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<SCwd> for String {
+    fn from(value: SCwd) -> Self {
+        value.to_string()
+    }
+}
+
+impl std::fmt::Display for SCwd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SCwd::Any => write!(f, "*"),
+            SCwd::Path(path) => write!(f, "{}", path),
+        }
+    }
+}
+
+/// Resources limited with setrlimit(2), named after the RLIMIT_* constants
+#[derive(
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Debug,
+    Clone,
+    Copy,
+    Display,
+    EnumString,
+    EnumIter,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum SRLimitResource {
+    /// Address space size, in bytes
+    As,
+    /// Core file size, in bytes
+    Core,
+    /// CPU time, in seconds
+    Cpu,
+    /// Data segment size, in bytes
+    Data,
+    /// Created file size, in bytes
+    Fsize,
+    Locks,
+    /// Locked memory, in bytes
+    Memlock,
+    /// POSIX message queues size, in bytes
+    Msgqueue,
+    Nice,
+    /// Number of open file descriptors
+    Nofile,
+    /// Number of processes of the target user
+    Nproc,
+    Rss,
+    Rtprio,
+    /// Real-time CPU time, in microseconds
+    Rttime,
+    Sigpending,
+    /// Stack size, in bytes
+    Stack,
+}
+
+/// A limit value, a number or `unlimited`
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum SRLimitValue {
+    Limited(u64),
+    Unlimited,
+}
+
+impl FromStr for SRLimitValue {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("unlimited") {
+            return Ok(SRLimitValue::Unlimited);
+        }
+        s.parse()
+            .map(SRLimitValue::Limited)
+            .map_err(|_| format!("invalid limit {}, expected a number or unlimited", s))
+    }
+}
+
+impl std::fmt::Display for SRLimitValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SRLimitValue::Limited(value) => write!(f, "{}", value),
+            SRLimitValue::Unlimited => write!(f, "unlimited"),
+        }
+    }
+}
+
+impl Serialize for SRLimitValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            SRLimitValue::Limited(value) => serializer.serialize_u64(*value),
+            SRLimitValue::Unlimited => serializer.serialize_str("unlimited"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SRLimitValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawValue {
+            Number(u64),
+            String(String),
+        }
+        match RawValue::deserialize(deserializer)? {
+            RawValue::Number(value) => Ok(SRLimitValue::Limited(value)),
+            RawValue::String(value) => value.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(try_from = "RawSRLimit")]
+pub struct SRLimit {
+    pub soft: SRLimitValue,
+    pub hard: SRLimitValue,
+}
+
+#[derive(Deserialize)]
+struct RawSRLimit {
+    soft: SRLimitValue,
+    hard: SRLimitValue,
+}
+
+impl TryFrom<RawSRLimit> for SRLimit {
+    type Error = String;
+
+    fn try_from(raw: RawSRLimit) -> Result<Self, Self::Error> {
+        SRLimit::new(raw.soft, raw.hard)
+    }
+}
+
+impl SRLimit {
+    /// The soft limit cannot exceed the hard limit
+    pub fn new(soft: SRLimitValue, hard: SRLimitValue) -> Result<Self, String> {
+        if soft > hard {
+            return Err(format!(
+                "soft limit {} is greater than hard limit {}",
+                soft, hard
+            ));
+        }
+        Ok(SRLimit { soft, hard })
+    }
+}
+
+/// Limits by resource, a task limit replaces the role and global limit of the same resource
+pub type SRLimits = BTreeMap<SRLimitResource, SRLimit>;
+
+/// cgroup v2 interface files that a task may set
+#[derive(
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Debug,
+    Clone,
+    Copy,
+    Display,
+    EnumString,
+    EnumIter,
+)]
+pub enum SCgroupFile {
+    /// Memory limit in bytes, with an optional K, M, G suffix, or `max`
+    #[serde(rename = "memory.max")]
+    #[strum(serialize = "memory.max")]
+    MemoryMax,
+    /// `$MAX $PERIOD`: CPU time in microseconds allowed per period, `$MAX` may be `max`
+    #[serde(rename = "cpu.max")]
+    #[strum(serialize = "cpu.max")]
+    CpuMax,
+    /// Number of processes, or `max`
+    #[serde(rename = "pids.max")]
+    #[strum(serialize = "pids.max")]
+    PidsMax,
+}
+
+impl SCgroupFile {
+    /// Controller to enable in the parent cgroups
+    pub fn controller(&self) -> &'static str {
+        match self {
+            SCgroupFile::MemoryMax => "memory",
+            SCgroupFile::CpuMax => "cpu",
+            SCgroupFile::PidsMax => "pids",
+        }
+    }
+
+    /// Checks the value format, the kernel does the remaining checks when it is written
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        let valid = match self {
+            SCgroupFile::MemoryMax => {
+                let bytes = value
+                    .strip_suffix(['k', 'K', 'm', 'M', 'g', 'G', 't', 'T'])
+                    .unwrap_or(value);
+                value == "max" || is_number(bytes)
+            }
+            SCgroupFile::CpuMax => {
+                let mut parts = value.split(' ');
+                parts
+                    .next()
+                    .is_some_and(|max| max == "max" || is_number(max))
+                    && parts.next().is_none_or(is_number)
+                    && parts.next().is_none()
+            }
+            SCgroupFile::PidsMax => value == "max" || is_number(value),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("invalid {} value: {}", self, value))
+        }
+    }
+}
+
+/// cgroup v2 limits of the command, merged by file with the role and global limits.
+/// The command is placed in a dedicated cgroup as soon as this option is set, even empty.
+pub type SCgroup = BTreeMap<SCgroupFile, String>;
+
+fn default_readonly() -> Vec<String> {
+    vec!["/usr".to_string(), "/etc".to_string()]
+}
+
+fn default_private_tmp() -> bool {
+    true
+}
+
+/// Namespaces of the command. A private mount namespace is always created,
+/// with `readonly` paths remounted read-only and a private `/tmp`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Builder)]
+#[serde(rename_all = "kebab-case")]
+pub struct SSandbox {
+    #[serde(default = "default_readonly")]
+    #[builder(default = default_readonly())]
+    pub readonly: Vec<String>,
+    #[serde(default = "default_private_tmp")]
+    #[builder(default = default_private_tmp())]
+    pub private_tmp: bool,
+    /// New PID namespace, the command is its process 1 and /proc is remounted
+    #[serde(default, skip_serializing_if = "is_default")]
+    #[builder(default)]
+    pub pid: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    #[builder(default)]
+    pub ipc: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    #[builder(default)]
+    pub uts: bool,
+    /// New network namespace, only the loopback interface is available
+    #[serde(default, skip_serializing_if = "is_default")]
+    #[builder(default)]
+    pub net: bool,
+}
+
+impl Default for SSandbox {
+    fn default() -> Self {
+        SSandbox::builder().build()
+    }
+}
+
+/// Securebits of the command, see capabilities(7). A locked bit cannot be changed by the command.
+#[derive(
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Debug,
+    Display,
+    Clone,
+    Copy,
+    EnumIter,
+    EnumString,
+)]
+#[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum SSecureBit {
+    /// uid 0 gets no capabilities on exec
+    Noroot,
+    NorootLocked,
+    /// Capabilities are kept when switching from and to uid 0
+    NoSetuidFixup,
+    NoSetuidFixupLocked,
+    /// The command cannot keep its permitted capabilities when switching to a non-zero uid.
+    /// keep-caps itself is cleared by the exec of the command, only its lock is offered.
+    KeepCapsLocked,
+    /// Ambient capabilities cannot be raised
+    NoCapAmbientRaise,
+    NoCapAmbientRaiseLocked,
+}
+
+pub type SSecureBits = BTreeSet<SSecureBit>;
+
+impl From<SSecureBit> for Secbits {
+    fn from(bit: SSecureBit) -> Self {
+        match bit {
+            SSecureBit::Noroot => Secbits::NOROOT,
+            SSecureBit::NorootLocked => Secbits::NOROOT_LOCKED,
+            SSecureBit::NoSetuidFixup => Secbits::NO_SETUID_FIXUP,
+            SSecureBit::NoSetuidFixupLocked => Secbits::NO_SETUID_FIXUP_LOCKED,
+            SSecureBit::KeepCapsLocked => Secbits::KEEP_CAPS_LOCKED,
+            SSecureBit::NoCapAmbientRaise => Secbits::NO_CAP_AMBIENT_RAISE,
+            SSecureBit::NoCapAmbientRaiseLocked => Secbits::NO_CAP_AMBIENT_RAISE_LOCKED,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct Opt {
@@ -346,14 +887,65 @@ pub struct Opt {
     pub root: Option<SPrivileged>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bounding: Option<SBounding>,
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub authentication: Option<SAuthentication>,
+    #[serde(
+        default,
+        serialize_with = "serialize_authentication",
+        deserialize_with = "deserialize_authentication",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub authentication: Option<SAuthPolicy>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub execinfo: Option<SInfo>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub timeout: Option<STimeout>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub umask: Option<SUMask>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pty: Option<SPty>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record: Option<SRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<SCwd>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_absolute_path",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub chroot: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rlimits: Option<SRLimits>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cgroup: Option<SCgroup>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SSandbox>,
+    /// The command cannot execute other programs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noexec: Option<bool>,
+    /// Every exec of the command is checked against the commands of the task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intercept: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub securebits: Option<SSecureBits>,
+    /// The command and its children cannot gain privileges on exec, implied by `root: user`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_new_privs: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities_mode: Option<SCapabilitiesMode>,
+    /// The command is terminated once it runs longer, in HH:MM:SS
+    #[serde(
+        default,
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_runtime: Option<Duration>,
+    /// Program asking the password for `dosr --askpass`, only read from the global options
+    #[serde(
+        default,
+        deserialize_with = "deserialize_absolute_path",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub askpass: Option<String>,
     #[serde(default, flatten)]
     pub _extra_fields: Map<String, Value>,
 }
@@ -367,10 +959,24 @@ impl Opt {
         env: Option<SEnvOptions>,
         root: Option<SPrivileged>,
         bounding: Option<SBounding>,
-        authentication: Option<SAuthentication>,
+        #[builder(into)] authentication: Option<SAuthPolicy>,
         execinfo: Option<SInfo>,
         timeout: Option<STimeout>,
         umask: Option<SUMask>,
+        pty: Option<SPty>,
+        record: Option<SRecord>,
+        cwd: Option<SCwd>,
+        chroot: Option<String>,
+        rlimits: Option<SRLimits>,
+        cgroup: Option<SCgroup>,
+        sandbox: Option<SSandbox>,
+        noexec: Option<bool>,
+        intercept: Option<bool>,
+        securebits: Option<SSecureBits>,
+        no_new_privs: Option<bool>,
+        capabilities_mode: Option<SCapabilitiesMode>,
+        max_runtime: Option<Duration>,
+        askpass: Option<String>,
         #[builder(default)] _extra_fields: Map<String, Value>,
     ) -> Self {
         Opt {
@@ -383,6 +989,20 @@ impl Opt {
             execinfo,
             timeout,
             umask,
+            pty,
+            record,
+            cwd,
+            chroot,
+            rlimits,
+            cgroup,
+            sandbox,
+            noexec,
+            intercept,
+            securebits,
+            no_new_privs,
+            capabilities_mode,
+            max_runtime,
+            askpass,
             _extra_fields,
         }
     }
@@ -395,6 +1015,8 @@ impl Opt {
             .authentication(AUTHENTICATION)
             .execinfo(INFO)
             .umask(UMASK)
+            .pty(PTY)
+            .record(RECORD)
             .env(
                 SEnvOptions::builder(ENV_DEFAULT_BEHAVIOR)
                     .keep(ENV_KEEP_LIST)
@@ -623,6 +1245,28 @@ impl SBounding {
     }
 }
 
+impl SPty {
+    pub const fn try_parse(input: &str) -> std::result::Result<SPty, ConstParseError> {
+        match input {
+            _ if eq_str(input, "auto") => Ok(SPty::Auto),
+            _ if eq_str(input, "always") => Ok(SPty::Always),
+            _ if eq_str(input, "never") => Ok(SPty::Never),
+            _ => ConstParseError("SPty").panic(),
+        }
+    }
+}
+
+impl SRecord {
+    pub const fn try_parse(input: &str) -> std::result::Result<SRecord, ConstParseError> {
+        match input {
+            _ if eq_str(input, "disabled") => Ok(SRecord::Disabled),
+            _ if eq_str(input, "asciicast") => Ok(SRecord::Asciicast),
+            _ if eq_str(input, "ttyrec") => Ok(SRecord::Ttyrec),
+            _ => ConstParseError("SRecord").panic(),
+        }
+    }
+}
+
 impl SAuthentication {
     pub const fn try_parse(input: &str) -> std::result::Result<SAuthentication, ConstParseError> {
         match input {
@@ -767,6 +1411,30 @@ impl OptStack {
         }
     }
 
+    fn get_final_rlimits(&self) -> Option<SRLimits> {
+        let mut final_rlimits: Option<SRLimits> = None;
+        self.iter_in_options(|opt| {
+            if let Some(rlimits) = &opt.rlimits {
+                final_rlimits
+                    .get_or_insert_with(Default::default)
+                    .extend(rlimits.iter().map(|(k, v)| (*k, *v)));
+            }
+        });
+        final_rlimits
+    }
+
+    fn get_final_cgroup(&self) -> Option<SCgroup> {
+        let mut final_cgroup: Option<SCgroup> = None;
+        self.iter_in_options(|opt| {
+            if let Some(cgroup) = &opt.cgroup {
+                final_cgroup
+                    .get_or_insert_with(Default::default)
+                    .extend(cgroup.iter().map(|(k, v)| (*k, v.clone())));
+            }
+        });
+        final_cgroup
+    }
+
     fn get_final_path(&self) -> SPathOptions {
         let mut final_behavior = PathBehavior::Delete;
         let default = LinkedHashSet::new();
@@ -933,6 +1601,52 @@ impl OptStack {
         rc_refcell!(Opt::builder(self.get_level())
             .path(self.get_final_path())
             .env(self.get_final_env(None))
+            .maybe_rlimits(self.get_final_rlimits())
+            .maybe_cgroup(self.get_final_cgroup())
+            .maybe_sandbox(
+                self.find_in_options(|opt| opt.sandbox.clone().map(|sandbox| (opt.level, sandbox)))
+                    .map(|(_, sandbox)| sandbox),
+            )
+            .maybe_noexec(
+                self.find_in_options(|opt| opt.noexec.map(|noexec| (opt.level, noexec)))
+                    .map(|(_, noexec)| noexec),
+            )
+            .maybe_intercept(
+                self.find_in_options(|opt| opt.intercept.map(|intercept| (opt.level, intercept)))
+                    .map(|(_, intercept)| intercept),
+            )
+            .maybe_securebits(
+                self.find_in_options(|opt| {
+                    opt.securebits
+                        .clone()
+                        .map(|securebits| (opt.level, securebits))
+                })
+                .map(|(_, securebits)| securebits),
+            )
+            .maybe_no_new_privs(
+                self.find_in_options(|opt| {
+                    opt.no_new_privs
+                        .map(|no_new_privs| (opt.level, no_new_privs))
+                })
+                .map(|(_, no_new_privs)| no_new_privs),
+            )
+            .maybe_capabilities_mode(
+                self.find_in_options(|opt| opt.capabilities_mode.map(|mode| (opt.level, mode)))
+                    .map(|(_, mode)| mode),
+            )
+            .maybe_max_runtime(
+                self.find_in_options(|opt| opt.max_runtime.map(|max| (opt.level, max)))
+                    .map(|(_, max)| max),
+            )
+            .maybe_askpass(
+                self.find_in_options(|opt| {
+                    opt.askpass
+                        .clone()
+                        .filter(|_| opt.level == Level::Global)
+                        .map(|askpass| (opt.level, askpass))
+                })
+                .map(|(_, askpass)| askpass),
+            )
             .maybe_root(
                 self.find_in_options(|opt| opt.root.map(|root| (opt.level, root)))
                     .map(|(_, root)| root),
@@ -944,6 +1658,7 @@ impl OptStack {
             .maybe_authentication(
                 self.find_in_options(|opt| {
                     opt.authentication
+                        .clone()
                         .map(|authentication| (opt.level, authentication))
                 })
                 .map(|(_, authentication)| authentication),
@@ -952,6 +1667,22 @@ impl OptStack {
                 self.find_in_options(|opt| opt.timeout.clone().map(|timeout| (opt.level, timeout)))
                     .map(|(_, timeout)| timeout),
             )
+            .maybe_pty(
+                self.find_in_options(|opt| opt.pty.map(|pty| (opt.level, pty)))
+                    .map(|(_, pty)| pty),
+            )
+            .maybe_record(
+                self.find_in_options(|opt| opt.record.map(|record| (opt.level, record)))
+                    .map(|(_, record)| record),
+            )
+            .maybe_cwd(
+                self.find_in_options(|opt| opt.cwd.clone().map(|cwd| (opt.level, cwd)))
+                    .map(|(_, cwd)| cwd),
+            )
+            .maybe_chroot(
+                self.find_in_options(|opt| opt.chroot.clone().map(|chroot| (opt.level, chroot)))
+                    .map(|(_, chroot)| chroot),
+            )
             .build())
     }
 }
@@ -1211,7 +1942,7 @@ mod tests {
         assert_eq!(global_options.root.unwrap(), SPrivileged::Privileged);
         assert_eq!(global_options.bounding.unwrap(), SBounding::Ignore);
         assert_eq!(
-            global_options.authentication.unwrap(),
+            global_options.authentication.as_ref().unwrap().mode,
             SAuthentication::Perform
         );
         assert_eq!(
@@ -1256,7 +1987,10 @@ mod tests {
         ));
         assert_eq!(role_options.root.unwrap(), SPrivileged::Privileged);
         assert_eq!(role_options.bounding.unwrap(), SBounding::Strict);
-        assert_eq!(role_options.authentication.unwrap(), SAuthentication::Skip);
+        assert_eq!(
+            role_options.authentication.as_ref().unwrap().mode,
+            SAuthentication::Skip
+        );
         assert_eq!(
             role_options.timeout.as_ref().unwrap().duration.unwrap(),
             Duration::minutes(2)
@@ -1300,7 +2034,7 @@ mod tests {
         assert_eq!(task_options.root.unwrap(), SPrivileged::User);
         assert_eq!(task_options.bounding.unwrap(), SBounding::Strict);
         assert_eq!(
-            task_options.authentication.unwrap(),
+            task_options.authentication.as_ref().unwrap().mode,
             SAuthentication::Perform
         );
         assert_eq!(
@@ -1608,4 +2342,416 @@ mod tests {
         // This should serialize as just a string, not as a struct
         assert_tokens(&umask, &[Token::Str("644")]);
     }
+
+    #[test]
+    fn test_spty_serde() {
+        assert_tokens(
+            &SPty::Auto,
+            &[Token::UnitVariant {
+                name: "SPty",
+                variant: "auto",
+            }],
+        );
+        assert_tokens(
+            &SPty::Never,
+            &[Token::UnitVariant {
+                name: "SPty",
+                variant: "never",
+            }],
+        );
+        assert_eq!("Always".parse::<SPty>().unwrap(), SPty::Always);
+    }
+
+    #[test]
+    fn test_pty_task_overrides_global() {
+        let config = SConfig::builder()
+            .role(
+                SRole::builder("test")
+                    .task(
+                        STask::builder(1)
+                            .options(|opt| opt.pty(SPty::Never).build())
+                            .build(),
+                    )
+                    .build(),
+            )
+            .options(|opt| opt.pty(SPty::Always).build())
+            .build();
+        let binding = OptStack::from_task(config.task("test", 1).unwrap()).to_opt();
+        assert_eq!(binding.as_ref().borrow().pty, Some(SPty::Never));
+        let binding = OptStack::from_roles(config).to_opt();
+        assert_eq!(binding.as_ref().borrow().pty, Some(SPty::Always));
+    }
+
+    #[test]
+    fn test_srecord_serde() {
+        assert_tokens(
+            &SRecord::Asciicast,
+            &[Token::UnitVariant {
+                name: "SRecord",
+                variant: "asciicast",
+            }],
+        );
+        assert_eq!(SRecord::default(), RECORD);
+        assert_eq!(SRecord::try_parse("ttyrec").unwrap(), SRecord::Ttyrec);
+        assert_eq!(Opt::level_default().record, Some(RECORD));
+        let opt: Opt = serde_json::from_str(r#"{"record": "ttyrec"}"#).unwrap();
+        assert_eq!(opt.record, Some(SRecord::Ttyrec));
+    }
+
+    #[test]
+    fn test_scwd_serde() {
+        assert_tokens(&SCwd::Any, &[Token::Str("*")]);
+        assert_tokens(&SCwd::Path("~/www".to_string()), &[Token::Str("~/www")]);
+        assert_de_tokens_error::<SCwd>(
+            &[Token::Str("relative")],
+            "invalid working directory relative, expected an absolute path, ~ or *",
+        );
+        let opt: Opt = serde_json::from_str(r#"{"cwd": "/srv", "chroot": "/jail"}"#).unwrap();
+        assert_eq!(opt.cwd, Some(SCwd::Path("/srv".to_string())));
+        assert_eq!(opt.chroot.as_deref(), Some("/jail"));
+        assert!(serde_json::from_str::<Opt>(r#"{"chroot": "jail"}"#).is_err());
+    }
+
+    #[test]
+    fn test_cwd_task_overrides_role() {
+        let config = SConfig::builder()
+            .role(
+                SRole::builder("test")
+                    .task(
+                        STask::builder(1)
+                            .options(|opt| opt.cwd(SCwd::Any).build())
+                            .build(),
+                    )
+                    .options(|opt| {
+                        opt.cwd(SCwd::Path("~".to_string()))
+                            .chroot("/jail".to_string())
+                            .build()
+                    })
+                    .build(),
+            )
+            .build();
+        let binding = OptStack::from_task(config.task("test", 1).unwrap()).to_opt();
+        assert_eq!(binding.as_ref().borrow().cwd, Some(SCwd::Any));
+        assert_eq!(binding.as_ref().borrow().chroot.as_deref(), Some("/jail"));
+    }
+
+    #[test]
+    fn test_srlimits_serde() {
+        let opt: Opt = serde_json::from_str(
+            r#"{"rlimits": {"nofile": {"soft": 1024, "hard": 4096}, "core": {"soft": 0, "hard": "unlimited"}}}"#,
+        )
+        .unwrap();
+        let rlimits = opt.rlimits.unwrap();
+        assert_eq!(
+            rlimits[&SRLimitResource::Nofile],
+            SRLimit::new(SRLimitValue::Limited(1024), SRLimitValue::Limited(4096)).unwrap()
+        );
+        assert_eq!(
+            rlimits[&SRLimitResource::Core].hard,
+            SRLimitValue::Unlimited
+        );
+        assert_eq!(
+            serde_json::to_string(&rlimits).unwrap(),
+            r#"{"core":{"soft":0,"hard":"unlimited"},"nofile":{"soft":1024,"hard":4096}}"#
+        );
+        assert!(
+            serde_json::from_str::<SRLimits>(r#"{"nofile": {"soft": "many", "hard": 1}}"#).is_err()
+        );
+        assert!(SRLimit::new(SRLimitValue::Unlimited, SRLimitValue::Limited(1)).is_err());
+        assert!(serde_json::from_str::<SRLimit>(r#"{"soft": 4096, "hard": 1024}"#).is_err());
+        assert_eq!("NPROC".parse(), Ok(SRLimitResource::Nproc));
+    }
+
+    #[test]
+    fn test_rlimits_merged_by_resource() {
+        let limit = |value| {
+            SRLimit::new(SRLimitValue::Limited(value), SRLimitValue::Limited(value)).unwrap()
+        };
+        let config = SConfig::builder()
+            .role(
+                SRole::builder("test")
+                    .task(
+                        STask::builder(1)
+                            .options(|opt| {
+                                opt.rlimits(SRLimits::from([(SRLimitResource::Nofile, limit(64))]))
+                                    .build()
+                            })
+                            .build(),
+                    )
+                    .options(|opt| {
+                        opt.rlimits(SRLimits::from([
+                            (SRLimitResource::Nofile, limit(1024)),
+                            (SRLimitResource::Nproc, limit(100)),
+                        ]))
+                        .build()
+                    })
+                    .build(),
+            )
+            .build();
+        let binding = OptStack::from_task(config.task("test", 1).unwrap()).to_opt();
+        let rlimits = binding.as_ref().borrow().rlimits.clone().unwrap();
+        assert_eq!(rlimits[&SRLimitResource::Nofile], limit(64));
+        assert_eq!(rlimits[&SRLimitResource::Nproc], limit(100));
+    }
+
+    #[test]
+    fn test_scgroup() {
+        let opt: Opt = serde_json::from_str(
+            r#"{"cgroup": {"memory.max": "512M", "cpu.max": "50000 100000", "pids.max": "max"}}"#,
+        )
+        .unwrap();
+        let cgroup = opt.cgroup.unwrap();
+        assert_eq!(cgroup[&SCgroupFile::MemoryMax], "512M");
+        assert_eq!(cgroup[&SCgroupFile::CpuMax], "50000 100000");
+        assert_eq!("pids.max".parse(), Ok(SCgroupFile::PidsMax));
+        assert_eq!(SCgroupFile::MemoryMax.to_string(), "memory.max");
+        assert!(SCgroupFile::MemoryMax.check("512M").is_ok());
+        assert!(SCgroupFile::MemoryMax.check("1073741824").is_ok());
+        assert!(SCgroupFile::MemoryMax.check("512MM").is_err());
+        assert!(SCgroupFile::CpuMax.check("max 100000").is_ok());
+        assert!(SCgroupFile::CpuMax.check("50000").is_ok());
+        assert!(SCgroupFile::CpuMax.check("50000 max").is_err());
+        assert!(SCgroupFile::PidsMax.check("100").is_ok());
+        assert!(SCgroupFile::PidsMax.check("-1").is_err());
+        let opt: Opt = serde_json::from_str(r#"{"cgroup": {}}"#).unwrap();
+        assert_eq!(opt.cgroup, Some(SCgroup::new()));
+    }
+
+    #[test]
+    fn test_ssandbox_serde() {
+        let opt: Opt = serde_json::from_str(r#"{"sandbox": {}}"#).unwrap();
+        let sandbox = opt.sandbox.unwrap();
+        assert_eq!(sandbox, SSandbox::default());
+        assert_eq!(sandbox.readonly, vec!["/usr", "/etc"]);
+        assert!(sandbox.private_tmp && !sandbox.pid && !sandbox.net);
+        assert_eq!(
+            serde_json::to_string(&sandbox).unwrap(),
+            r#"{"readonly":["/usr","/etc"],"private-tmp":true}"#
+        );
+        let opt: Opt = serde_json::from_str(
+            r#"{"sandbox": {"readonly": ["/"], "private-tmp": false, "pid": true, "net": true}}"#,
+        )
+        .unwrap();
+        let sandbox = opt.sandbox.unwrap();
+        assert_eq!(sandbox.readonly, vec!["/"]);
+        assert!(!sandbox.private_tmp && sandbox.pid && sandbox.net && !sandbox.ipc);
+    }
+
+    #[test]
+    fn test_noexec_task_overrides_global() {
+        let config = SConfig::builder()
+            .role(
+                SRole::builder("test")
+                    .task(
+                        STask::builder(1)
+                            .options(|opt| opt.noexec(false).build())
+                            .build(),
+                    )
+                    .task(STask::builder(2).build())
+                    .build(),
+            )
+            .options(|opt| opt.noexec(true).build())
+            .build();
+        let binding = OptStack::from_task(config.task("test", 1).unwrap()).to_opt();
+        assert_eq!(binding.as_ref().borrow().noexec, Some(false));
+        let binding = OptStack::from_task(config.task("test", 2).unwrap()).to_opt();
+        assert_eq!(binding.as_ref().borrow().noexec, Some(true));
+        let opt: Opt = serde_json::from_str(r#"{"noexec": true, "intercept": true}"#).unwrap();
+        assert_eq!(opt.noexec, Some(true));
+        assert_eq!(opt.intercept, Some(true));
+    }
+
+    #[test]
+    fn test_securebits_task_overrides_global() {
+        let config = SConfig::builder()
+            .role(
+                SRole::builder("test")
+                    .task(
+                        STask::builder(1)
+                            .options(|opt| {
+                                opt.securebits(SSecureBits::from([SSecureBit::Noroot]))
+                                    .no_new_privs(false)
+                                    .build()
+                            })
+                            .build(),
+                    )
+                    .task(STask::builder(2).build())
+                    .build(),
+            )
+            .options(|opt| {
+                opt.securebits(SSecureBits::from([SSecureBit::KeepCapsLocked]))
+                    .no_new_privs(true)
+                    .build()
+            })
+            .build();
+        let binding = OptStack::from_task(config.task("test", 1).unwrap()).to_opt();
+        let opt = binding.as_ref().borrow();
+        assert_eq!(
+            opt.securebits,
+            Some(SSecureBits::from([SSecureBit::Noroot]))
+        );
+        assert_eq!(opt.no_new_privs, Some(false));
+        let binding = OptStack::from_task(config.task("test", 2).unwrap()).to_opt();
+        let opt = binding.as_ref().borrow();
+        assert_eq!(
+            opt.securebits,
+            Some(SSecureBits::from([SSecureBit::KeepCapsLocked]))
+        );
+        assert_eq!(opt.no_new_privs, Some(true));
+        let opt: Opt = serde_json::from_str(
+            r#"{"securebits": ["noroot-locked", "noroot"], "no-new-privs": true}"#,
+        )
+        .unwrap();
+        let securebits = opt.securebits.unwrap();
+        assert_eq!(
+            securebits
+                .iter()
+                .fold(Secbits::empty(), |acc, bit| acc | (*bit).into()),
+            Secbits::NOROOT | Secbits::NOROOT_LOCKED
+        );
+        assert!(serde_json::from_str::<Opt>(r#"{"securebits": ["noroot-lock"]}"#).is_err());
+        assert!(serde_json::from_str::<Opt>(r#"{"securebits": ["keep-caps"]}"#).is_err());
+    }
+
+    #[test]
+    fn test_capabilities_mode() {
+        let config = SConfig::builder()
+            .role(
+                SRole::builder("test")
+                    .options(|opt| {
+                        opt.capabilities_mode(SCapabilitiesMode::Inheritable)
+                            .build()
+                    })
+                    .task(STask::builder(1).build())
+                    .build(),
+            )
+            .options(|opt| opt.capabilities_mode(SCapabilitiesMode::Ambient).build())
+            .build();
+        let binding = OptStack::from_task(config.task("test", 1).unwrap()).to_opt();
+        assert_eq!(
+            binding.as_ref().borrow().capabilities_mode,
+            Some(SCapabilitiesMode::Inheritable)
+        );
+        let opt: Opt = serde_json::from_str(r#"{"capabilities-mode": "inheritable"}"#).unwrap();
+        assert_eq!(opt.capabilities_mode, Some(SCapabilitiesMode::Inheritable));
+        assert_eq!(
+            "Ambient".parse::<SCapabilitiesMode>().unwrap(),
+            SCapabilitiesMode::Ambient
+        );
+        let opt: Opt =
+            serde_json::from_str(r#"{"capabilities-mode": "ambient-no-propagate"}"#).unwrap();
+        assert_eq!(
+            opt.capabilities_mode,
+            Some(SCapabilitiesMode::AmbientNoPropagate)
+        );
+        assert_eq!(
+            "Ambient-No-Propagate".parse::<SCapabilitiesMode>().unwrap(),
+            SCapabilitiesMode::AmbientNoPropagate
+        );
+        assert!(SCapabilitiesMode::AmbientNoPropagate
+            .unsupported()
+            .is_some());
+        assert!(SCapabilitiesMode::Ambient.unsupported().is_none());
+        assert!(SCapabilitiesMode::Inheritable.unsupported().is_none());
+        assert_eq!(SCapabilitiesMode::default(), SCapabilitiesMode::Ambient);
+    }
+
+    #[test]
+    fn test_askpass() {
+        let config = SConfig::builder()
+            .role(
+                SRole::builder("test")
+                    .task(
+                        STask::builder(1)
+                            .options(|opt| opt.askpass("/home/user/askpass".to_string()).build())
+                            .build(),
+                    )
+                    .build(),
+            )
+            .options(|opt| opt.askpass("/usr/bin/ssh-askpass".to_string()).build())
+            .build();
+        let binding = OptStack::from_task(config.task("test", 1).unwrap()).to_opt();
+        assert_eq!(
+            binding.as_ref().borrow().askpass.as_deref(),
+            Some("/usr/bin/ssh-askpass")
+        );
+        assert!(serde_json::from_str::<Opt>(r#"{"askpass": "ssh-askpass"}"#).is_err());
+    }
+
+    #[test]
+    fn test_authentication_policy() {
+        let opt: Opt = serde_json::from_str(r#"{"authentication": "skip"}"#).unwrap();
+        assert_eq!(opt.authentication, Some(SAuthentication::Skip.into()));
+        assert_eq!(
+            serde_json::to_value(&opt).unwrap()["authentication"],
+            "skip"
+        );
+        let opt: Opt = serde_json::from_str(
+            r#"{"authentication": {"service": "dosr-root", "prompt": "Root password: ", "retries": 2, "prompt-timeout": "00:00:30", "lecture": "Think before you type."}}"#,
+        )
+        .unwrap();
+        let policy = opt.authentication.as_ref().unwrap();
+        assert_eq!(policy.mode, SAuthentication::Perform);
+        assert_eq!(policy.service.as_deref(), Some("dosr-root"));
+        assert_eq!(policy.prompt.as_deref(), Some("Root password: "));
+        assert_eq!(policy.retries, Some(2));
+        assert_eq!(policy.prompt_timeout, Some(Duration::seconds(30)));
+        assert_eq!(policy.lecture.as_deref(), Some("Think before you type."));
+        let value = serde_json::to_value(&opt).unwrap();
+        assert_eq!(value["authentication"]["service"], "dosr-root");
+        assert_eq!(value["authentication"]["prompt-timeout"], "00:00:30");
+        assert!(serde_json::from_str::<Opt>(r#"{"authentication": "maybe"}"#).is_err());
+    }
+
+    #[test]
+    fn test_authentication_user() {
+        let opt: Opt = serde_json::from_str(r#"{"authentication": {"user": "target"}}"#).unwrap();
+        assert_eq!(
+            opt.authentication.as_ref().unwrap().user,
+            Some(SAuthUser::Target)
+        );
+        let opt: Opt =
+            serde_json::from_str(r#"{"authentication": {"user": {"account": "root"}}}"#).unwrap();
+        assert_eq!(
+            opt.authentication.as_ref().unwrap().user,
+            Some(SAuthUser::Account("root".into()))
+        );
+        assert_eq!(
+            serde_json::to_value(&opt).unwrap()["authentication"]["user"]["account"],
+            "root"
+        );
+    }
+
+    #[test]
+    fn test_max_runtime() {
+        let config = SConfig::builder()
+            .role(
+                SRole::builder("test")
+                    .task(
+                        STask::builder(1)
+                            .options(|opt| opt.max_runtime(Duration::minutes(30)).build())
+                            .build(),
+                    )
+                    .task(STask::builder(2).build())
+                    .build(),
+            )
+            .options(|opt| opt.max_runtime(Duration::hours(2)).build())
+            .build();
+        let binding = OptStack::from_task(config.task("test", 1).unwrap()).to_opt();
+        assert_eq!(
+            binding.as_ref().borrow().max_runtime,
+            Some(Duration::minutes(30))
+        );
+        let binding = OptStack::from_task(config.task("test", 2).unwrap()).to_opt();
+        assert_eq!(
+            binding.as_ref().borrow().max_runtime,
+            Some(Duration::hours(2))
+        );
+        let opt: Opt = serde_json::from_str(r#"{"max-runtime": "01:30:00"}"#).unwrap();
+        assert_eq!(opt.max_runtime, Some(Duration::minutes(90)));
+        assert_eq!(
+            serde_json::to_value(&opt).unwrap()["max-runtime"],
+            "01:30:00"
+        );
+    }
 }
@@ -1,6 +1,7 @@
 use std::cmp::Ordering;
 
 use bon::Builder;
+use serde::{ser::SerializeSeq, Serialize, Serializer};
 use strum::EnumIs;
 
 use crate::util::{
@@ -10,7 +11,8 @@ use crate::util::{
 
 use super::actor::{DGroupType, DGroups, DUserType, SGroupType, SGroups, SUserType};
 
-#[derive(PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Debug, EnumIs, Default)]
+#[derive(PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Debug, EnumIs, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
 #[repr(u32)]
 // Matching user groups for the role
 pub enum ActorMatchMin {
@@ -20,7 +22,8 @@ pub enum ActorMatchMin {
     NoMatch = HARDENED_ENUM_VALUE_2,
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, EnumIs, Default)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, EnumIs, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
 #[repr(u32)]
 pub enum HardenedBool {
     #[default]
@@ -39,9 +42,10 @@ impl ActorMatchMin {
     }
 }
 
-#[derive(PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Debug)]
+#[derive(PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Debug, Serialize)]
 
 // Matching setuid and setgid for the role
+#[serde(rename_all = "kebab-case")]
 pub struct SetuidMin {
     is_root: bool,
 }
@@ -68,7 +72,8 @@ impl From<u32> for SetuidMin {
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct SetgidMin {
     is_root: bool,
     nb_groups: usize,
@@ -123,7 +128,8 @@ impl Ord for SetgidMin {
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct SetUserMin {
     pub uid: Option<SetuidMin>,
     pub gid: Option<SetgidMin>,
@@ -141,7 +147,8 @@ impl Ord for SetUserMin {
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Builder)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Builder, Serialize)]
+#[serde(rename_all = "kebab-case")]
 #[builder(const)]
 pub struct CmdMin {
     #[builder(default = HardenedBool::False, with = || HardenedBool::True, name = "matching")]
@@ -163,6 +170,24 @@ bitflags::bitflags! {
     }
 }
 
+/// Bitflags are serialized as the list of their flag names
+fn serialize_flag_names<'a, S: Serializer>(
+    names: impl Iterator<Item = &'a str>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut seq = serializer.serialize_seq(None)?;
+    for name in names {
+        seq.serialize_element(name)?;
+    }
+    seq.end()
+}
+
+impl Serialize for CmdOrder {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_flag_names(self.iter_names().map(|(name, _)| name), serializer)
+    }
+}
+
 impl CmdMin {
     pub const MATCH: CmdMin = CmdMin::builder().matching().build();
 
@@ -191,7 +216,8 @@ impl CmdMin {
     }
 }
 
-#[derive(PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Debug, Default)]
+#[derive(PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Debug, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
 #[repr(u32)]
 pub enum CapsMin {
     #[default]
@@ -217,7 +243,14 @@ bitflags::bitflags! {
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Builder)]
+impl Serialize for SecurityMin {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_flag_names(self.iter_names().map(|(name, _)| name), serializer)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Builder, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct TaskScore {
     #[builder(default)]
     pub cmd_min: CmdMin,
@@ -227,7 +260,8 @@ pub struct TaskScore {
     pub setuser_min: SetUserMin,
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Builder)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Builder, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct Score {
     #[builder(default)]
     pub user_min: ActorMatchMin,
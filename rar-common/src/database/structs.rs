@@ -327,8 +327,7 @@ pub enum SCommand {
     Complex(Value),
 }
 
-#[cfg_attr(test, derive(Clone))]
-#[derive(PartialEq, Eq, Debug, Default)]
+#[derive(PartialEq, Eq, Debug, Default, Clone)]
 pub struct SCommands {
     pub default: Option<SetBehavior>,
     pub add: Vec<SCommand>,
@@ -710,7 +709,7 @@ mod tests {
             .is_some_and(|s| s == "check_env"));
         assert!(options.root.as_ref().unwrap().is_privileged());
         assert!(options.bounding.as_ref().unwrap().is_ignore());
-        assert_eq!(options.authentication, Some(SAuthentication::Skip));
+        assert_eq!(options.authentication, Some(SAuthentication::Skip.into()));
 
         let timeout = options.timeout.as_ref().unwrap();
         assert_eq!(timeout.type_field, Some(TimestampType::PPID));
@@ -960,7 +959,7 @@ mod tests {
             .is_some_and(|s| s == "check_env"));
         assert!(options.root.as_ref().unwrap().is_privileged());
         assert!(options.bounding.as_ref().unwrap().is_ignore());
-        assert_eq!(options.authentication, Some(SAuthentication::Skip));
+        assert_eq!(options.authentication, Some(SAuthentication::Skip.into()));
 
         let timeout = options.timeout.as_ref().unwrap();
         assert_eq!(timeout.type_field, Some(TimestampType::PPID));
@@ -16,8 +16,8 @@ use nix::fcntl::{Flock, FlockArg};
 use serde::Serialize;
 
 use crate::database::options::{
-    EnvBehavior, PathBehavior, SAuthentication, SBounding, SInfo, SPrivileged, SUMask,
-    TimestampType,
+    EnvBehavior, PathBehavior, SAuthentication, SBounding, SInfo, SPrivileged, SPty, SRecord,
+    SUMask, TimestampType,
 };
 
 #[cfg(feature = "finder")]
@@ -189,6 +189,11 @@ pub const UMASK: SUMask = SUMask(result::unwrap_or!(
 pub const INFO: SInfo =
     result::unwrap_or!(SInfo::try_parse(env!("RAR_EXEC_INFO_DISPLAY")), SInfo::Hide);
 
+pub const PTY: SPty = result::unwrap_or!(SPty::try_parse(env!("RAR_PTY")), SPty::Auto);
+
+pub const RECORD: SRecord =
+    result::unwrap_or!(SRecord::try_parse(env!("RAR_RECORD")), SRecord::Disabled);
+
 #[macro_export]
 macro_rules! upweak {
     ($e:expr) => {
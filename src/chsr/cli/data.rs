@@ -10,8 +10,9 @@ use rar_common::{
     database::{
         actor::{SActor, SGroups, SUserType},
         options::{
-            EnvBehavior, EnvKey, OptType, PathBehavior, SAuthentication, SBounding, SInfo,
-            SPrivileged, SUMask, TimestampType,
+            EnvBehavior, EnvKey, OptType, PathBehavior, SAuthUser, SAuthentication, SBounding,
+            SCapabilitiesMode, SCgroupFile, SInfo, SPrivileged, SPty, SRLimitResource,
+            SRLimitValue, SRecord, SSecureBits, SUMask, TimestampType,
         },
         structs::{IdTask, SetBehavior},
     },
@@ -65,6 +66,16 @@ pub enum TimeoutOpt {
     MaxUsage,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum AuthSetting {
+    Service,
+    Prompt,
+    Lecture,
+    Retries,
+    PromptTimeout,
+    User,
+}
+
 #[derive(Debug, Default)]
 pub struct Inputs {
     pub action: InputAction,
@@ -95,8 +106,26 @@ pub struct Inputs {
     pub options_root: Option<SPrivileged>,
     pub options_bounding: Option<SBounding>,
     pub options_auth: Option<SAuthentication>,
+    pub options_auth_setting: Option<AuthSetting>,
+    pub options_auth_value: Option<String>,
+    pub options_auth_user: Option<SAuthUser>,
     pub options_execinfo: Option<SInfo>,
     pub options_umask: Option<SUMask>,
+    pub options_pty: Option<SPty>,
+    pub options_record: Option<SRecord>,
+    pub options_cwd: Option<String>,
+    pub options_chroot: Option<String>,
+    pub options_askpass: Option<String>,
+    pub options_rlimit_resource: Option<SRLimitResource>,
+    pub options_rlimit_soft: Option<SRLimitValue>,
+    pub options_rlimit_hard: Option<SRLimitValue>,
+    pub options_cgroup_file: Option<SCgroupFile>,
+    pub options_cgroup_value: Option<String>,
+    pub options_noexec: Option<bool>,
+    pub options_intercept: Option<bool>,
+    pub options_securebits: Option<SSecureBits>,
+    pub options_no_new_privs: Option<bool>,
+    pub options_capabilities_mode: Option<SCapabilitiesMode>,
     pub convertion: Option<Convertion>,
     pub convert_reconfigure: bool,
 }
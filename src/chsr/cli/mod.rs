@@ -590,6 +590,12 @@ mod tests {
 
         // Authentication option helpers
         fn assert_authentication_option(&self, expected: &Option<SAuthentication>) {
+            let settings_ref = self.opt(Level::Task);
+            let task_ref = settings_ref.as_ref().borrow();
+            assert_eq!(task_ref.authentication.as_ref().map(|a| a.mode), *expected);
+        }
+
+        fn assert_authentication_policy(&self, expected: &Option<SAuthPolicy>) {
             let settings_ref = self.opt(Level::Task);
             let task_ref = settings_ref.as_ref().borrow();
             assert_eq!(task_ref.authentication, *expected);
@@ -608,6 +614,92 @@ mod tests {
             let task_ref = settings_ref.as_ref().borrow();
             assert_eq!(task_ref.umask, *expected);
         }
+
+        // SPty option helpers
+        fn assert_pty_option(&self, expected: &Option<SPty>) {
+            let settings_ref = self.opt(Level::Task);
+            let task_ref = settings_ref.as_ref().borrow();
+            assert_eq!(task_ref.pty, *expected);
+        }
+
+        // SRecord option helpers
+        fn assert_record_option(&self, expected: &Option<SRecord>) {
+            let settings_ref = self.opt(Level::Task);
+            let task_ref = settings_ref.as_ref().borrow();
+            assert_eq!(task_ref.record, *expected);
+        }
+
+        fn assert_noexec_option(&self, expected: Option<bool>) {
+            let settings_ref = self.opt(Level::Task);
+            let task_ref = settings_ref.as_ref().borrow();
+            assert_eq!(task_ref.noexec, expected);
+        }
+
+        fn assert_intercept_option(&self, expected: Option<bool>) {
+            let settings_ref = self.opt(Level::Task);
+            let task_ref = settings_ref.as_ref().borrow();
+            assert_eq!(task_ref.intercept, expected);
+        }
+
+        fn assert_securebits_option(&self, expected: Option<&[SSecureBit]>) {
+            let settings_ref = self.opt(Level::Task);
+            let task_ref = settings_ref.as_ref().borrow();
+            assert_eq!(
+                task_ref.securebits,
+                expected.map(|bits| bits.iter().copied().collect())
+            );
+        }
+
+        fn assert_capabilities_mode_option(&self, expected: Option<SCapabilitiesMode>) {
+            let settings_ref = self.opt(Level::Task);
+            let task_ref = settings_ref.as_ref().borrow();
+            assert_eq!(task_ref.capabilities_mode, expected);
+        }
+
+        fn assert_max_runtime_option(&self, expected: Option<chrono::Duration>) {
+            let settings_ref = self.opt(Level::Task);
+            let task_ref = settings_ref.as_ref().borrow();
+            assert_eq!(task_ref.max_runtime, expected);
+        }
+
+        fn assert_no_new_privs_option(&self, expected: Option<bool>) {
+            let settings_ref = self.opt(Level::Task);
+            let task_ref = settings_ref.as_ref().borrow();
+            assert_eq!(task_ref.no_new_privs, expected);
+        }
+
+        // SCwd and chroot option helpers
+        fn assert_cwd_option(&self, expected: &Option<SCwd>) {
+            let settings_ref = self.opt(Level::Task);
+            let task_ref = settings_ref.as_ref().borrow();
+            assert_eq!(task_ref.cwd, *expected);
+        }
+
+        fn assert_chroot_option(&self, expected: Option<&str>) {
+            let settings_ref = self.opt(Level::Task);
+            let task_ref = settings_ref.as_ref().borrow();
+            assert_eq!(task_ref.chroot.as_deref(), expected);
+        }
+
+        // SCgroup option helpers
+        fn assert_cgroup_option(&self, expected: Option<SCgroup>) {
+            let settings_ref = self.opt(Level::Task);
+            let task_ref = settings_ref.as_ref().borrow();
+            assert_eq!(task_ref.cgroup, expected);
+        }
+
+        // SRLimits option helpers
+        fn assert_rlimit_option(&self, resource: SRLimitResource, expected: Option<SRLimit>) {
+            let settings_ref = self.opt(Level::Task);
+            let task_ref = settings_ref.as_ref().borrow();
+            assert_eq!(
+                task_ref
+                    .rlimits
+                    .as_ref()
+                    .and_then(|rlimits| rlimits.get(&resource).copied()),
+                expected
+            );
+        }
     }
 
     fn setup(name: &str) -> Defer<impl FnOnce()> {
@@ -1354,6 +1446,50 @@ mod tests {
         ctx.assert_authentication_option(&None);
     }
 
+    #[test]
+    fn test_r_complete_t_t_complete_o_auth_policy() {
+        let (ctx, _defer) = TestContext::new("r_complete_t_t_complete_o_auth_policy");
+
+        ctx.assert_command_success("r complete t t_complete o auth service dosr-admin");
+        ctx.assert_command_success("r complete t t_complete o auth prompt Root password:");
+        ctx.assert_command_success("r complete t t_complete o auth retries 2");
+        ctx.assert_command_success("r complete t t_complete o auth prompt-timeout 00:00:30");
+        ctx.assert_command_success("r complete t t_complete o auth lecture rm -rf is forever");
+        let mut policy = SAuthPolicy::builder()
+            .service("dosr-admin")
+            .prompt("Root password:")
+            .retries(2)
+            .prompt_timeout(chrono::Duration::seconds(30))
+            .lecture("rm -rf is forever")
+            .build();
+        ctx.assert_authentication_policy(&Some(policy.clone()));
+
+        // the mode keeps the other settings
+        ctx.assert_command_success("r complete t t_complete o auth skip");
+        policy.mode = SAuthentication::Skip;
+        ctx.assert_authentication_policy(&Some(policy.clone()));
+
+        ctx.assert_command_success("r complete t t_complete o auth lecture del");
+        ctx.assert_command_success("r complete t t_complete o auth prompt-timeout del");
+        policy.lecture = None;
+        policy.prompt_timeout = None;
+        ctx.assert_authentication_policy(&Some(policy.clone()));
+
+        ctx.assert_command_success("r complete t t_complete o auth user target");
+        policy.user = Some(SAuthUser::Target);
+        ctx.assert_authentication_policy(&Some(policy.clone()));
+        ctx.assert_command_success("r complete t t_complete o auth user account root");
+        policy.user = Some(SAuthUser::Account("root".into()));
+        ctx.assert_authentication_policy(&Some(policy.clone()));
+        ctx.assert_command_success("r complete t t_complete o auth user del");
+        policy.user = None;
+        ctx.assert_authentication_policy(&Some(policy));
+
+        assert!(ctx
+            .run_command("r complete t t_complete o auth retries 300")
+            .is_err());
+    }
+
     #[test]
     fn test_r_complete_t_t_complete_o_execinfo() {
         let (ctx, _defer) = TestContext::new("r_complete_t_t_complete_o_execinfo");
@@ -1385,6 +1521,256 @@ mod tests {
         ctx.assert_umask_option(&None);
     }
 
+    #[test]
+    fn test_r_complete_t_t_complete_o_pty() {
+        let (ctx, _defer) = TestContext::new("r_complete_t_t_complete_o_pty");
+
+        ctx.assert_command_success("r complete t t_complete o pty never");
+        ctx.assert_pty_option(&Some(SPty::Never));
+
+        ctx.assert_command_success("r complete t t_complete o pty always");
+        ctx.assert_pty_option(&Some(SPty::Always));
+
+        ctx.assert_command_success("r complete t t_complete o pty unset");
+        ctx.assert_pty_option(&None);
+    }
+
+    #[test]
+    fn test_r_complete_t_t_complete_o_record() {
+        let (ctx, _defer) = TestContext::new("r_complete_t_t_complete_o_record");
+
+        ctx.assert_command_success("r complete t t_complete o record asciicast");
+        ctx.assert_record_option(&Some(SRecord::Asciicast));
+
+        ctx.assert_command_success("r complete t t_complete o record ttyrec");
+        ctx.assert_record_option(&Some(SRecord::Ttyrec));
+
+        ctx.assert_command_success("r complete t t_complete o record unset");
+        ctx.assert_record_option(&None);
+    }
+
+    #[test]
+    fn test_r_complete_t_t_complete_o_noexec() {
+        let (ctx, _defer) = TestContext::new("r_complete_t_t_complete_o_noexec");
+
+        ctx.assert_command_success("r complete t t_complete o noexec true");
+        ctx.assert_noexec_option(Some(true));
+
+        ctx.assert_command_success("r complete t t_complete o noexec false");
+        ctx.assert_noexec_option(Some(false));
+
+        ctx.assert_command_success("r complete t t_complete o noexec unset");
+        ctx.assert_noexec_option(None);
+    }
+
+    #[test]
+    fn test_r_complete_t_t_complete_o_intercept() {
+        let (ctx, _defer) = TestContext::new("r_complete_t_t_complete_o_intercept");
+
+        ctx.assert_command_success("r complete t t_complete o intercept true");
+        ctx.assert_intercept_option(Some(true));
+
+        ctx.assert_command_success("r complete t t_complete o intercept del");
+        ctx.assert_intercept_option(None);
+    }
+
+    #[test]
+    fn test_r_complete_t_t_complete_o_securebits() {
+        let (ctx, _defer) = TestContext::new("r_complete_t_t_complete_o_securebits");
+
+        ctx.assert_command_success("r complete t t_complete o securebits set noroot,noroot-locked");
+        ctx.assert_securebits_option(Some(&[SSecureBit::Noroot, SSecureBit::NorootLocked]));
+
+        ctx.assert_command_success("r complete t t_complete o securebits add keep-caps-locked");
+        ctx.assert_securebits_option(Some(&[
+            SSecureBit::Noroot,
+            SSecureBit::NorootLocked,
+            SSecureBit::KeepCapsLocked,
+        ]));
+
+        ctx.assert_command_success("r complete t t_complete o securebits del noroot-locked");
+        ctx.assert_securebits_option(Some(&[SSecureBit::Noroot, SSecureBit::KeepCapsLocked]));
+
+        ctx.assert_command_success("r complete t t_complete o securebits purge");
+        ctx.assert_securebits_option(None);
+
+        assert!(ctx
+            .run_command("r complete t t_complete o securebits add noroot-lock")
+            .is_err());
+        // cleared by exec, it would never apply to the command
+        assert!(ctx
+            .run_command("r complete t t_complete o securebits add keep-caps")
+            .is_err());
+    }
+
+    #[test]
+    fn test_r_complete_t_t_complete_o_capabilities_mode() {
+        let (ctx, _defer) = TestContext::new("r_complete_t_t_complete_o_capabilities_mode");
+
+        ctx.assert_command_success("r complete t t_complete o capabilities-mode inheritable");
+        ctx.assert_capabilities_mode_option(Some(SCapabilitiesMode::Inheritable));
+
+        ctx.assert_command_success("r complete t t_complete o caps-mode ambient");
+        ctx.assert_capabilities_mode_option(Some(SCapabilitiesMode::Ambient));
+
+        // Linux cannot keep the ambient capabilities from reaching the programs the command runs
+        assert!(ctx
+            .run_command("r complete t t_complete o caps-mode ambient-no-propagate")
+            .is_err());
+        ctx.assert_capabilities_mode_option(Some(SCapabilitiesMode::Ambient));
+
+        ctx.assert_command_success("r complete t t_complete o capabilities-mode unset");
+        ctx.assert_capabilities_mode_option(None);
+    }
+
+    #[test]
+    fn test_r_complete_t_t_complete_o_max_runtime() {
+        let (ctx, _defer) = TestContext::new("r_complete_t_t_complete_o_max_runtime");
+
+        ctx.assert_command_success("r complete t t_complete o max-runtime 01:30:00");
+        ctx.assert_max_runtime_option(Some(chrono::Duration::minutes(90)));
+
+        ctx.assert_command_success("r complete t t_complete o max-runtime 45");
+        ctx.assert_max_runtime_option(Some(chrono::Duration::seconds(45)));
+
+        ctx.assert_command_success("r complete t t_complete o max-runtime del");
+        ctx.assert_max_runtime_option(None);
+
+        assert!(ctx
+            .run_command("r complete t t_complete o max-runtime forever")
+            .is_err());
+    }
+
+    #[test]
+    fn test_r_complete_t_t_complete_o_no_new_privs() {
+        let (ctx, _defer) = TestContext::new("r_complete_t_t_complete_o_no_new_privs");
+
+        ctx.assert_command_success("r complete t t_complete o no-new-privs true");
+        ctx.assert_no_new_privs_option(Some(true));
+
+        ctx.assert_command_success("r complete t t_complete o no-new-privs del");
+        ctx.assert_no_new_privs_option(None);
+    }
+
+    #[test]
+    fn test_r_complete_t_t_complete_o_cwd() {
+        let (ctx, _defer) = TestContext::new("r_complete_t_t_complete_o_cwd");
+
+        ctx.assert_command_success("r complete t t_complete o cwd /var/www");
+        ctx.assert_cwd_option(&Some(SCwd::Path("/var/www".to_string())));
+
+        ctx.assert_command_success("r complete t t_complete o cwd ~/projects");
+        ctx.assert_cwd_option(&Some(SCwd::Path("~/projects".to_string())));
+
+        ctx.assert_command_success("r complete t t_complete o cwd *");
+        ctx.assert_cwd_option(&Some(SCwd::Any));
+
+        ctx.assert_command_success("r complete t t_complete o cwd unset");
+        ctx.assert_cwd_option(&None);
+
+        assert!(ctx
+            .run_command("r complete t t_complete o cwd relative")
+            .is_err());
+        // only the home of the target user, rejected by the parser
+        for cwd in ["~bob", "~foo/bar"] {
+            assert!(
+                Cli::parse(Rule::cli, &format!("r complete t t_complete o cwd {}", cwd)).is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_r_complete_t_t_complete_o_chroot() {
+        let (ctx, _defer) = TestContext::new("r_complete_t_t_complete_o_chroot");
+
+        ctx.assert_command_success("r complete t t_complete o chroot /srv/jail");
+        ctx.assert_chroot_option(Some("/srv/jail"));
+
+        ctx.assert_command_success("r complete t t_complete o chroot del");
+        ctx.assert_chroot_option(None);
+    }
+
+    #[test]
+    fn test_o_askpass() {
+        let (ctx, _defer) = TestContext::new("o_askpass");
+        let askpass = || {
+            let settings_ref = ctx.opt(Level::Global);
+            let global_ref = settings_ref.as_ref().borrow();
+            global_ref.askpass.clone()
+        };
+
+        ctx.assert_command_success("o askpass /usr/bin/ssh-askpass");
+        assert_eq!(askpass().as_deref(), Some("/usr/bin/ssh-askpass"));
+
+        // the roles are not trusted with the askpass program
+        assert!(ctx
+            .run_command("r complete t t_complete o askpass /home/user/askpass")
+            .is_err());
+
+        ctx.assert_command_success("o askpass del");
+        assert_eq!(askpass(), None);
+    }
+
+    #[test]
+    fn test_r_complete_t_t_complete_o_rlimit() {
+        let (ctx, _defer) = TestContext::new("r_complete_t_t_complete_o_rlimit");
+
+        ctx.assert_command_success("r complete t t_complete o rlimit set nofile 1024 4096");
+        ctx.assert_rlimit_option(
+            SRLimitResource::Nofile,
+            Some(SRLimit::new(SRLimitValue::Limited(1024), SRLimitValue::Limited(4096)).unwrap()),
+        );
+
+        ctx.assert_command_success("r complete t t_complete o rlimit set core 0");
+        ctx.assert_rlimit_option(
+            SRLimitResource::Core,
+            Some(SRLimit::new(SRLimitValue::Limited(0), SRLimitValue::Limited(0)).unwrap()),
+        );
+
+        ctx.assert_command_success("r complete t t_complete o rlimit set nproc 100 unlimited");
+        ctx.assert_rlimit_option(
+            SRLimitResource::Nproc,
+            Some(SRLimit::new(SRLimitValue::Limited(100), SRLimitValue::Unlimited).unwrap()),
+        );
+
+        assert!(ctx
+            .run_command("r complete t t_complete o rlimit set nofile 4096 1024")
+            .is_err());
+
+        ctx.assert_command_success("r complete t t_complete o rlimit del nofile");
+        ctx.assert_rlimit_option(SRLimitResource::Nofile, None);
+        ctx.assert_rlimit_option(
+            SRLimitResource::Core,
+            Some(SRLimit::new(SRLimitValue::Limited(0), SRLimitValue::Limited(0)).unwrap()),
+        );
+
+        ctx.assert_command_success("r complete t t_complete o rlimit purge");
+        ctx.assert_rlimit_option(SRLimitResource::Core, None);
+    }
+
+    #[test]
+    fn test_r_complete_t_t_complete_o_cgroup() {
+        let (ctx, _defer) = TestContext::new("r_complete_t_t_complete_o_cgroup");
+
+        ctx.assert_command_success("r complete t t_complete o cgroup set memory.max 512M");
+        ctx.assert_command_success("r complete t t_complete o cgroup set cpu.max 50000 100000");
+        ctx.assert_cgroup_option(Some(SCgroup::from([
+            (SCgroupFile::MemoryMax, "512M".to_string()),
+            (SCgroupFile::CpuMax, "50000 100000".to_string()),
+        ])));
+
+        assert!(ctx
+            .run_command("r complete t t_complete o cgroup set pids.max lots")
+            .is_err());
+
+        ctx.assert_command_success("r complete t t_complete o cgroup del memory.max");
+        ctx.assert_command_success("r complete t t_complete o cgroup del cpu.max");
+        ctx.assert_cgroup_option(Some(SCgroup::new()));
+
+        ctx.assert_command_success("r complete t t_complete o cgroup purge");
+        ctx.assert_cgroup_option(None);
+    }
+
     fn normalize_json_object(value: Value) -> Value {
         match value {
             Value::Object(map) => {
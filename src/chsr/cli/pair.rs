@@ -11,7 +11,7 @@ use crate::cli::data::{RoleType, TaskType};
 use rar_common::{
     database::{
         actor::{SActor, SGroupType},
-        options::{EnvBehavior, OptType, PathBehavior, TimestampType},
+        options::{EnvBehavior, OptType, PathBehavior, SAuthUser, TimestampType},
         structs::{IdTask, SetBehavior},
     },
     StorageMethod,
@@ -390,6 +390,34 @@ fn match_pair(pair: &Pair<Rule>, inputs: &mut Inputs) -> Result<(), Box<dyn Erro
                 inputs.options_type = Some(OptType::ExecInfo);
             } else if pair.as_str() == "umask" {
                 inputs.options_type = Some(OptType::UMask);
+            } else if pair.as_str() == "pty" {
+                inputs.options_type = Some(OptType::Pty);
+            } else if pair.as_str() == "record" {
+                inputs.options_type = Some(OptType::Record);
+            } else if pair.as_str() == "cwd" {
+                inputs.options_type = Some(OptType::Cwd);
+            } else if pair.as_str() == "chroot" {
+                inputs.options_type = Some(OptType::Chroot);
+            } else if pair.as_str() == "rlimits" {
+                inputs.options_type = Some(OptType::RLimits);
+            } else if pair.as_str() == "cgroup" {
+                inputs.options_type = Some(OptType::Cgroup);
+            } else if pair.as_str() == "sandbox" {
+                inputs.options_type = Some(OptType::Sandbox);
+            } else if pair.as_str() == "noexec" {
+                inputs.options_type = Some(OptType::NoExec);
+            } else if pair.as_str() == "intercept" {
+                inputs.options_type = Some(OptType::Intercept);
+            } else if pair.as_str() == "securebits" {
+                inputs.options_type = Some(OptType::SecureBits);
+            } else if pair.as_str() == "no-new-privs" {
+                inputs.options_type = Some(OptType::NoNewPrivs);
+            } else if pair.as_str() == "capabilities-mode" {
+                inputs.options_type = Some(OptType::CapabilitiesMode);
+            } else if pair.as_str() == "max-runtime" {
+                inputs.options_type = Some(OptType::MaxRuntime);
+            } else if pair.as_str() == "askpass" {
+                inputs.options_type = Some(OptType::Askpass);
             } else {
                 unreachable!("Unknown option type: {}", pair.as_str())
             }
@@ -430,6 +458,37 @@ fn match_pair(pair: &Pair<Rule>, inputs: &mut Inputs) -> Result<(), Box<dyn Erro
             inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
             inputs.options_auth = Some(pair.as_str().parse().unwrap_or_default());
         }
+        Rule::opt_auth_prompt_timeout => {
+            inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
+            inputs.options_auth_setting = Some(AuthSetting::PromptTimeout);
+        }
+        Rule::opt_auth_retries => {
+            inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
+            inputs.options_auth_setting = Some(AuthSetting::Retries);
+        }
+        Rule::opt_auth_user => {
+            inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
+            inputs.options_auth_setting = Some(AuthSetting::User);
+        }
+        Rule::opt_auth_user_value => {
+            inputs.options_auth_user = Some(match pair.clone().into_inner().next() {
+                Some(account) => SAuthUser::Account(account.as_str().into()),
+                None if pair.as_str() == "target" => SAuthUser::Target,
+                None => SAuthUser::Caller,
+            });
+        }
+        Rule::opt_auth_text => {
+            inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
+            inputs.options_auth_setting = Some(match pair.as_str() {
+                "service" => AuthSetting::Service,
+                "prompt" => AuthSetting::Prompt,
+                "lecture" => AuthSetting::Lecture,
+                _ => unreachable!("Unknown authentication setting: {}", pair.as_str()),
+            });
+        }
+        Rule::opt_auth_retries_value | Rule::opt_auth_text_value => {
+            inputs.options_auth_value = Some(pair.as_str().to_string());
+        }
         Rule::opt_execinfo_args => {
             inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
             inputs.options_execinfo = Some(pair.as_str().parse().unwrap_or_default());
@@ -438,6 +497,80 @@ fn match_pair(pair: &Pair<Rule>, inputs: &mut Inputs) -> Result<(), Box<dyn Erro
             inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
             inputs.options_umask = Some(pair.as_str().parse().unwrap_or_default());
         }
+        Rule::opt_pty_args => {
+            inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
+            inputs.options_pty = Some(pair.as_str().parse().unwrap_or_default());
+        }
+        Rule::opt_record_args => {
+            inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
+            inputs.options_record = Some(pair.as_str().parse().unwrap_or_default());
+        }
+        Rule::opt_noexec_args => {
+            inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
+            inputs.options_noexec = Some(pair.as_str().parse().unwrap_or_default());
+        }
+        Rule::opt_intercept_args => {
+            inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
+            inputs.options_intercept = Some(pair.as_str().parse().unwrap_or_default());
+        }
+        Rule::opt_no_new_privs_args => {
+            inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
+            inputs.options_no_new_privs = Some(pair.as_str().parse().unwrap_or_default());
+        }
+        Rule::opt_capabilities_mode_args => {
+            inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
+            inputs.options_capabilities_mode = Some(pair.as_str().parse().unwrap_or_default());
+        }
+        Rule::opt_max_runtime => {
+            inputs.options_type = Some(OptType::MaxRuntime);
+        }
+        Rule::opt_max_runtime_args => {
+            inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
+        }
+        Rule::opt_securebits => {
+            inputs.options_type = Some(OptType::SecureBits);
+        }
+        Rule::securebit => {
+            if let Ok(bit) = pair.as_str().parse() {
+                inputs
+                    .options_securebits
+                    .get_or_insert_with(Default::default)
+                    .insert(bit);
+            }
+        }
+        Rule::opt_cwd_args => {
+            inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
+            inputs.options_cwd = Some(pair.as_str().to_string());
+        }
+        Rule::opt_rlimit => {
+            inputs.options_type = Some(OptType::RLimits);
+        }
+        Rule::rlimit_resource => {
+            inputs.options_rlimit_resource = pair.as_str().parse().ok();
+        }
+        Rule::rlimit_soft => {
+            inputs.options_rlimit_soft = pair.as_str().parse().ok();
+        }
+        Rule::rlimit_hard => {
+            inputs.options_rlimit_hard = pair.as_str().parse().ok();
+        }
+        Rule::opt_cgroup => {
+            inputs.options_type = Some(OptType::Cgroup);
+        }
+        Rule::cgroup_file => {
+            inputs.options_cgroup_file = pair.as_str().parse().ok();
+        }
+        Rule::cgroup_value => {
+            inputs.options_cgroup_value = Some(pair.as_str().to_string());
+        }
+        Rule::opt_askpass_args => {
+            inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
+            inputs.options_askpass = Some(pair.as_str().to_string());
+        }
+        Rule::opt_chroot_args => {
+            inputs.action = InputAction::Set; // If del it will be overwritten by the parse loop
+            inputs.options_chroot = Some(pair.as_str().to_string());
+        }
         Rule::all => {
             if inputs.role_id.is_some() && inputs.task_id.is_none() {
                 inputs.role_type = Some(RoleType::All);
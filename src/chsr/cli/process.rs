@@ -9,7 +9,7 @@ use log::debug;
 
 use rar_common::{
     database::{
-        options::{Opt, OptType},
+        options::{Opt, OptType, SRLimit},
         structs::{IdTask, RoleGetter},
     },
     FullSettings,
@@ -252,6 +252,26 @@ pub fn process_input(
             ..
         } => set_authentication(rconfig, role_id, task_id, None),
 
+        Inputs {
+            // chsr o authentication prompt "Root password: "
+            action: InputAction::Set | InputAction::Del,
+            role_id,
+            task_id,
+            options_auth_setting: Some(setting),
+            options_auth_value,
+            options_auth_user,
+            timeout_duration,
+            ..
+        } => set_authentication_setting(
+            rconfig,
+            role_id,
+            task_id,
+            setting,
+            options_auth_value,
+            timeout_duration,
+            options_auth_user,
+        ),
+
         Inputs {
             // chsr o execinfo hide|show
             action: InputAction::Set,
@@ -286,6 +306,296 @@ pub fn process_input(
             ..
         } => set_umask(rconfig, role_id, task_id, None),
 
+        Inputs {
+            // chsr o pty auto|always|never
+            action: InputAction::Set,
+            role_id,
+            task_id,
+            options_pty: Some(options_pty),
+            ..
+        } => set_pty(rconfig, role_id, task_id, Some(options_pty)),
+
+        Inputs {
+            // chsr o pty del
+            action: InputAction::Del,
+            role_id,
+            task_id,
+            options_pty: Some(_),
+            ..
+        } => set_pty(rconfig, role_id, task_id, None),
+
+        Inputs {
+            // chsr o record disabled|asciicast|ttyrec
+            action: InputAction::Set,
+            role_id,
+            task_id,
+            options_record: Some(options_record),
+            ..
+        } => set_record(rconfig, role_id, task_id, Some(options_record)),
+
+        Inputs {
+            // chsr o record del
+            action: InputAction::Del,
+            role_id,
+            task_id,
+            options_record: Some(_),
+            ..
+        } => set_record(rconfig, role_id, task_id, None),
+
+        Inputs {
+            // chsr o noexec true|false
+            action: InputAction::Set,
+            role_id,
+            task_id,
+            options_noexec: Some(options_noexec),
+            ..
+        } => set_noexec(rconfig, role_id, task_id, Some(options_noexec)),
+
+        Inputs {
+            // chsr o noexec del
+            action: InputAction::Del,
+            role_id,
+            task_id,
+            options_noexec: Some(_),
+            ..
+        } => set_noexec(rconfig, role_id, task_id, None),
+
+        Inputs {
+            // chsr o intercept true|false
+            action: InputAction::Set,
+            role_id,
+            task_id,
+            options_intercept: Some(options_intercept),
+            ..
+        } => set_intercept(rconfig, role_id, task_id, Some(options_intercept)),
+
+        Inputs {
+            // chsr o intercept del
+            action: InputAction::Del,
+            role_id,
+            task_id,
+            options_intercept: Some(_),
+            ..
+        } => set_intercept(rconfig, role_id, task_id, None),
+
+        Inputs {
+            // chsr o no-new-privs true|false
+            action: InputAction::Set,
+            role_id,
+            task_id,
+            options_no_new_privs: Some(options_no_new_privs),
+            ..
+        } => set_no_new_privs(rconfig, role_id, task_id, Some(options_no_new_privs)),
+
+        Inputs {
+            // chsr o no-new-privs del
+            action: InputAction::Del,
+            role_id,
+            task_id,
+            options_no_new_privs: Some(_),
+            ..
+        } => set_no_new_privs(rconfig, role_id, task_id, None),
+
+        Inputs {
+            // chsr o capabilities-mode ambient|inheritable|ambient-no-propagate
+            action: InputAction::Set,
+            role_id,
+            task_id,
+            options_capabilities_mode: Some(options_capabilities_mode),
+            ..
+        } => set_capabilities_mode(rconfig, role_id, task_id, Some(options_capabilities_mode)),
+
+        Inputs {
+            // chsr o capabilities-mode del
+            action: InputAction::Del,
+            role_id,
+            task_id,
+            options_capabilities_mode: Some(_),
+            ..
+        } => set_capabilities_mode(rconfig, role_id, task_id, None),
+
+        Inputs {
+            // chsr o max-runtime 01:30:00
+            action: InputAction::Set,
+            role_id,
+            task_id,
+            options_type: Some(OptType::MaxRuntime),
+            timeout_duration: Some(max_runtime),
+            ..
+        } => set_max_runtime(rconfig, role_id, task_id, Some(max_runtime)),
+
+        Inputs {
+            // chsr o max-runtime del
+            action: InputAction::Del,
+            role_id,
+            task_id,
+            options_type: Some(OptType::MaxRuntime),
+            ..
+        } => set_max_runtime(rconfig, role_id, task_id, None),
+
+        Inputs {
+            // chsr o securebits set noroot,noroot-locked
+            action: InputAction::Set,
+            role_id,
+            task_id,
+            options_type: Some(OptType::SecureBits),
+            options_securebits: Some(bits),
+            ..
+        } => set_securebits(rconfig, role_id, task_id, Some(bits)),
+
+        Inputs {
+            // chsr o securebits add keep-caps-locked
+            action: InputAction::Add,
+            role_id,
+            task_id,
+            options_type: Some(OptType::SecureBits),
+            options_securebits: Some(bits),
+            ..
+        } => securebits_add(rconfig, role_id, task_id, bits),
+
+        Inputs {
+            // chsr o securebits del keep-caps-locked
+            action: InputAction::Del,
+            role_id,
+            task_id,
+            options_type: Some(OptType::SecureBits),
+            options_securebits: Some(bits),
+            ..
+        } => securebits_del(rconfig, role_id, task_id, bits),
+
+        Inputs {
+            // chsr o securebits purge
+            action: InputAction::Purge,
+            role_id,
+            task_id,
+            options_type: Some(OptType::SecureBits),
+            ..
+        } => set_securebits(rconfig, role_id, task_id, None),
+
+        Inputs {
+            // chsr o cwd /path|~/path|*
+            action: InputAction::Set,
+            role_id,
+            task_id,
+            options_cwd: Some(options_cwd),
+            ..
+        } => set_cwd(rconfig, role_id, task_id, Some(options_cwd.parse()?)),
+
+        Inputs {
+            // chsr o cwd del
+            action: InputAction::Del,
+            role_id,
+            task_id,
+            options_cwd: Some(_),
+            ..
+        } => set_cwd(rconfig, role_id, task_id, None),
+
+        Inputs {
+            // chsr o chroot /path
+            action: InputAction::Set,
+            role_id,
+            task_id,
+            options_chroot: Some(options_chroot),
+            ..
+        } => set_chroot(rconfig, role_id, task_id, Some(options_chroot)),
+
+        Inputs {
+            // chsr o chroot del
+            action: InputAction::Del,
+            role_id,
+            task_id,
+            options_chroot: Some(_),
+            ..
+        } => set_chroot(rconfig, role_id, task_id, None),
+
+        Inputs {
+            // chsr o askpass /usr/bin/ssh-askpass
+            action: InputAction::Set,
+            role_id,
+            task_id,
+            options_askpass: Some(options_askpass),
+            ..
+        } => set_askpass(rconfig, role_id, task_id, Some(options_askpass)),
+
+        Inputs {
+            // chsr o askpass del
+            action: InputAction::Del,
+            role_id,
+            task_id,
+            options_askpass: Some(_),
+            ..
+        } => set_askpass(rconfig, role_id, task_id, None),
+
+        Inputs {
+            // chsr o rlimit set nofile 1024 4096
+            action: InputAction::Set,
+            role_id,
+            task_id,
+            options_type: Some(OptType::RLimits),
+            options_rlimit_resource: Some(resource),
+            options_rlimit_soft: Some(soft),
+            options_rlimit_hard: hard,
+            ..
+        } => rlimit_set(
+            rconfig,
+            role_id,
+            task_id,
+            resource,
+            SRLimit::new(soft, hard.unwrap_or(soft))?,
+        ),
+
+        Inputs {
+            // chsr o rlimit del nofile
+            action: InputAction::Del,
+            role_id,
+            task_id,
+            options_type: Some(OptType::RLimits),
+            options_rlimit_resource: Some(resource),
+            ..
+        } => rlimit_del(rconfig, role_id, task_id, resource),
+
+        Inputs {
+            // chsr o rlimit purge
+            action: InputAction::Purge,
+            role_id,
+            task_id,
+            options_type: Some(OptType::RLimits),
+            ..
+        } => rlimit_purge(rconfig, role_id, task_id),
+
+        Inputs {
+            // chsr o cgroup set memory.max 512M
+            action: InputAction::Set,
+            role_id,
+            task_id,
+            options_type: Some(OptType::Cgroup),
+            options_cgroup_file: Some(file),
+            options_cgroup_value: Some(value),
+            ..
+        } => {
+            file.check(&value)?;
+            cgroup_set(rconfig, role_id, task_id, file, value)
+        }
+
+        Inputs {
+            // chsr o cgroup del memory.max
+            action: InputAction::Del,
+            role_id,
+            task_id,
+            options_type: Some(OptType::Cgroup),
+            options_cgroup_file: Some(file),
+            ..
+        } => cgroup_del(rconfig, role_id, task_id, file),
+
+        Inputs {
+            // chsr o cgroup purge
+            action: InputAction::Purge,
+            role_id,
+            task_id,
+            options_type: Some(OptType::Cgroup),
+            ..
+        } => cgroup_purge(rconfig, role_id, task_id),
+
         Inputs {
             // chsr o path whitelist set a:b:c
             action: InputAction::Set,
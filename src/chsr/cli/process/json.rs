@@ -3,11 +3,12 @@ use std::{cell::RefCell, collections::HashMap, error::Error, ops::Deref, rc::Rc}
 use linked_hash_set::LinkedHashSet;
 use log::{debug, warn};
 
-use crate::cli::data::{InputAction, RoleType, SetListType, TaskType, TimeoutOpt};
+use crate::cli::data::{AuthSetting, InputAction, RoleType, SetListType, TaskType, TimeoutOpt};
 
 use rar_common::database::{
     options::{
-        EnvBehavior, EnvKey, Opt, OptStack, OptType, PathBehavior, SEnvOptions, SPathOptions,
+        EnvBehavior, EnvKey, Opt, OptStack, OptType, PathBehavior, SCapabilitiesMode, SCgroupFile,
+        SCwd, SEnvOptions, SPathOptions, SPty, SRLimit, SRLimitResource, SRecord, SSecureBits,
         STimeout, SUMask,
     },
     structs::{
@@ -82,6 +83,65 @@ fn list_task(
                         OptType::UMask => {
                             println!("{}", serde_json::to_string_pretty(&opt.umask).unwrap());
                         }
+                        OptType::Pty => {
+                            println!("{}", serde_json::to_string_pretty(&opt.pty).unwrap());
+                        }
+                        OptType::Record => {
+                            println!("{}", serde_json::to_string_pretty(&opt.record).unwrap());
+                        }
+                        OptType::Cwd => {
+                            println!("{}", serde_json::to_string_pretty(&opt.cwd).unwrap());
+                        }
+                        OptType::Chroot => {
+                            println!("{}", serde_json::to_string_pretty(&opt.chroot).unwrap());
+                        }
+                        OptType::Askpass => {
+                            println!("{}", serde_json::to_string_pretty(&opt.askpass).unwrap());
+                        }
+                        OptType::RLimits => {
+                            println!("{}", serde_json::to_string_pretty(&opt.rlimits).unwrap());
+                        }
+                        OptType::Cgroup => {
+                            println!("{}", serde_json::to_string_pretty(&opt.cgroup).unwrap());
+                        }
+                        OptType::Sandbox => {
+                            println!("{}", serde_json::to_string_pretty(&opt.sandbox).unwrap());
+                        }
+                        OptType::NoExec => {
+                            println!("{}", serde_json::to_string_pretty(&opt.noexec).unwrap());
+                        }
+                        OptType::Intercept => {
+                            println!("{}", serde_json::to_string_pretty(&opt.intercept).unwrap());
+                        }
+                        OptType::SecureBits => {
+                            println!("{}", serde_json::to_string_pretty(&opt.securebits).unwrap());
+                        }
+                        OptType::CapabilitiesMode => {
+                            println!(
+                                "{}",
+                                serde_json::to_string_pretty(&opt.capabilities_mode).unwrap()
+                            );
+                        }
+                        OptType::MaxRuntime => {
+                            println!(
+                                "{}",
+                                serde_json::to_string_pretty(&opt.max_runtime.map(|max| {
+                                    format!(
+                                        "{:02}:{:02}:{:02}",
+                                        max.num_hours(),
+                                        max.num_minutes() % 60,
+                                        max.num_seconds() % 60
+                                    )
+                                }))
+                                .unwrap()
+                            );
+                        }
+                        OptType::NoNewPrivs => {
+                            println!(
+                                "{}",
+                                serde_json::to_string_pretty(&opt.no_new_privs).unwrap()
+                            );
+                        }
                     }
                 } else {
                     println!("{}", serde_json::to_string_pretty(&rcopt)?);
@@ -614,7 +674,42 @@ pub fn set_authentication(
 ) -> Result<bool, Box<dyn Error>> {
     debug!("chsr o auth set");
     perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
-        opt.as_ref().borrow_mut().authentication = options_auth;
+        let mut opt = opt.as_ref().borrow_mut();
+        match options_auth {
+            // keep the service, prompt and other settings of the policy
+            Some(mode) => opt.authentication.get_or_insert_with(Default::default).mode = mode,
+            None => opt.authentication = None,
+        }
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn set_authentication_setting(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    setting: AuthSetting,
+    value: Option<String>,
+    prompt_timeout: Option<chrono::TimeDelta>,
+    user: Option<rar_common::database::options::SAuthUser>,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o auth {:?} set", setting);
+    let retries = match (&setting, &value) {
+        (AuthSetting::Retries, Some(retries)) => Some(retries.parse::<u8>()?),
+        _ => None,
+    };
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        let mut opt = opt.as_ref().borrow_mut();
+        let policy = opt.authentication.get_or_insert_with(Default::default);
+        match setting {
+            AuthSetting::Service => policy.service = value.clone(),
+            AuthSetting::Prompt => policy.prompt = value.clone(),
+            AuthSetting::Lecture => policy.lecture = value.clone(),
+            AuthSetting::Retries => policy.retries = retries,
+            AuthSetting::PromptTimeout => policy.prompt_timeout = prompt_timeout,
+            AuthSetting::User => policy.user = user.clone(),
+        }
         Ok(())
     })?;
     Ok(true)
@@ -648,6 +743,309 @@ pub fn set_umask(
     Ok(true)
 }
 
+pub fn set_pty(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    options_pty: Option<SPty>,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o pty set");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref().borrow_mut().pty = options_pty;
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn set_record(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    options_record: Option<SRecord>,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o record set");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref().borrow_mut().record = options_record;
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn set_noexec(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    options_noexec: Option<bool>,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o noexec set");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref().borrow_mut().noexec = options_noexec;
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn set_intercept(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    options_intercept: Option<bool>,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o intercept set");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref().borrow_mut().intercept = options_intercept;
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn set_no_new_privs(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    options_no_new_privs: Option<bool>,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o no-new-privs set");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref().borrow_mut().no_new_privs = options_no_new_privs;
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn set_capabilities_mode(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    options_capabilities_mode: Option<SCapabilitiesMode>,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o capabilities-mode set");
+    if let Some(reason) = options_capabilities_mode
+        .as_ref()
+        .and_then(SCapabilitiesMode::unsupported)
+    {
+        return Err(reason.into());
+    }
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref().borrow_mut().capabilities_mode = options_capabilities_mode;
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn set_max_runtime(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    max_runtime: Option<chrono::TimeDelta>,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o max-runtime set");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref().borrow_mut().max_runtime = max_runtime;
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn set_securebits(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    options_securebits: Option<SSecureBits>,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o securebits set");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref().borrow_mut().securebits = options_securebits.clone();
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn securebits_add(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    bits: SSecureBits,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o securebits add");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref()
+            .borrow_mut()
+            .securebits
+            .get_or_insert_with(Default::default)
+            .extend(bits.iter().copied());
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn securebits_del(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    bits: SSecureBits,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o securebits del");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        let mut binding = opt.as_ref().borrow_mut();
+        if let Some(securebits) = binding.securebits.as_mut() {
+            securebits.retain(|bit| !bits.contains(bit));
+            if securebits.is_empty() {
+                binding.securebits = None;
+            }
+        }
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn set_cwd(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    options_cwd: Option<SCwd>,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o cwd set");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref().borrow_mut().cwd = options_cwd.clone();
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+/// dosr only reads the askpass program from the global options
+pub fn set_askpass(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    options_askpass: Option<String>,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o askpass set");
+    if role_id.is_some() {
+        return Err("The askpass program can only be set in the global options".into());
+    }
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref().borrow_mut().askpass = options_askpass.clone();
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn set_chroot(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    options_chroot: Option<String>,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o chroot set");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref().borrow_mut().chroot = options_chroot.clone();
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn rlimit_set(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    resource: SRLimitResource,
+    limit: SRLimit,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o rlimit set");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref()
+            .borrow_mut()
+            .rlimits
+            .get_or_insert_with(Default::default)
+            .insert(resource, limit);
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn rlimit_del(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    resource: SRLimitResource,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o rlimit del");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        let mut binding = opt.as_ref().borrow_mut();
+        if let Some(rlimits) = binding.rlimits.as_mut() {
+            rlimits.remove(&resource);
+            if rlimits.is_empty() {
+                binding.rlimits = None;
+            }
+        }
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn rlimit_purge(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o rlimit purge");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref().borrow_mut().rlimits = None;
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn cgroup_set(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    file: SCgroupFile,
+    value: String,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o cgroup set");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref()
+            .borrow_mut()
+            .cgroup
+            .get_or_insert_with(Default::default)
+            .insert(file, value.clone());
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+/// The cgroup option is kept when its last limit is removed, the command is still placed in a cgroup
+pub fn cgroup_del(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+    file: SCgroupFile,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o cgroup del");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        if let Some(cgroup) = opt.as_ref().borrow_mut().cgroup.as_mut() {
+            cgroup.remove(&file);
+        }
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+pub fn cgroup_purge(
+    rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
+    role_id: Option<String>,
+    task_id: Option<IdTask>,
+) -> Result<bool, Box<dyn Error>> {
+    debug!("chsr o cgroup purge");
+    perform_on_target_opt(rconfig, role_id, task_id, |opt: Rc<RefCell<Opt>>| {
+        opt.as_ref().borrow_mut().cgroup = None;
+        Ok(())
+    })?;
+    Ok(true)
+}
+
 pub fn path_set(
     rconfig: &Rc<RefCell<rar_common::database::structs::SConfig>>,
     role_id: Option<String>,
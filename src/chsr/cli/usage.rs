@@ -89,8 +89,22 @@ chsr role [role_name] task [task_name] options [option] [operation]
   {BOLD}bounding{RST} [policy]             Defines when dropped capabilities are permanently removed in the instantiated process. (unset, strict, ignore, inherit)
   {BOLD}timeout{RST}                       Manage timeout settings (set, unset).
   {BOLD}authentication{RST} [policy]       Defines if user needs to authenticate (unset, skip, perform, inherit).
+  {BOLD}authentication{RST} [setting]      Defines the PAM service, prompt, lecture (text|unset), retries (number|unset), prompt-timeout (time|unset) and user whose password is asked (caller, target, account <name>, unset).
   {BOLD}execinfo{RST} [policy]             Defines if user can see execution settings (unset, display, hide, inherit).
   {BOLD}umask, mask{RST} [del|umask]       Defines the umask for the executed command (unset or 022).
+  {BOLD}pty{RST} [policy]                  Defines when a pseudo-terminal is allocated for the command (unset, auto, always, never).
+  {BOLD}record{RST} [format]               Records the command output for later replay with dosr --replay (unset, disabled, asciicast, ttyrec).
+  {BOLD}noexec{RST} [true|false]           Prevents the command from executing other programs, e.g. shell escapes (unset, true, false).
+  {BOLD}intercept{RST} [true|false]        Checks every program executed by the command against the task commands (unset, true, false).
+  {BOLD}securebits{RST}                    Manage the securebits of the command (set, add, del <bits>, purge), e.g. add noroot,noroot-locked.
+  {BOLD}no-new-privs{RST} [true|false]     Prevents the command from gaining privileges on exec, always set with root user (unset, true, false).
+  {BOLD}capabilities-mode{RST} [mode]      Defines how the task capabilities are given to the command (unset, ambient, inheritable, ambient-no-propagate is rejected).
+  {BOLD}max-runtime{RST} [hh:mm:ss]        Terminates the command once it runs longer, dosr -T may only lower it (unset, hh:mm:ss).
+  {BOLD}cwd{RST} [directory]               Defines the working directory of the command (unset, /path, ~ for the target home, * to allow dosr --chdir).
+  {BOLD}chroot{RST} [directory]            Defines the root directory of the command (unset or /path).
+  {BOLD}askpass{RST} [program]             Defines the program asking the password for dosr -A, global options only (unset or /path).
+  {BOLD}rlimit{RST}                        Manage resource limits (set <resource> <soft> [hard], del <resource>, purge), e.g. set nofile 1024 4096.
+  {BOLD}cgroup{RST}                        Manage the cgroup v2 limits of the command (set <memory.max|cpu.max|pids.max> <value>, del <file>, purge).
 ",UNDERLINE=UNDERLINE, BOLD=BOLD, RST=RST);
 
 const RAR_USAGE_OPTIONS_PATH :&str = formatcp!("{UNDERLINE}{BOLD}Path options:{RST}
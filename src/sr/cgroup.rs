@@ -0,0 +1,172 @@
+//! Places the command in a dedicated cgroup v2, `/sys/fs/cgroup/rootasrole/<role>/<task>/<pid>`,
+//! where `<pid>` is the dosr process id, with the limits of the `cgroup` option.
+//! dosr creates the cgroup and opens its `cgroup.procs` file with its privileges, the command joins
+//! the cgroup by writing to this file descriptor before exec, as the kernel checks the opener credentials.
+//! The cgroup is removed once the command exits.
+
+use std::{
+    collections::BTreeSet,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use capctl::Cap;
+use log::{debug, error, warn};
+use rar_common::{database::options::SCgroup, util::with_privileges};
+
+use crate::{
+    error::{SrError, SrResult},
+    finder::BestExecSettings,
+};
+
+#[cfg(not(test))]
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+#[cfg(test)]
+const CGROUP_ROOT: &str = "target/cgroup";
+const CGROUP_BASE: &str = "rootasrole";
+
+pub struct Cgroup {
+    path: PathBuf,
+    procs: File,
+}
+
+/// Role and task names become directory names
+fn component(name: &str) -> SrResult<&str> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        error!("{:?} cannot be used as a cgroup name", name);
+        return Err(SrError::ConfigurationError);
+    }
+    Ok(name)
+}
+
+/// Controllers to enable in the parent cgroups, as written to `cgroup.subtree_control`
+fn controllers(limits: &SCgroup) -> String {
+    limits
+        .keys()
+        .map(|file| format!("+{}", file.controller()))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn write_file(path: &Path, value: &str) -> std::io::Result<()> {
+    debug!("Writing {} to {}", value, path.display());
+    OpenOptions::new()
+        .write(true)
+        .open(path)?
+        .write_all(value.as_bytes())
+}
+
+/// Creates the cgroup and writes the limits, the cgroup is removed on failure
+fn create_leaf(path: &Path, limits: &SCgroup) -> std::io::Result<File> {
+    std::fs::create_dir(path)?;
+    let configure = || -> std::io::Result<File> {
+        for (file, value) in limits {
+            write_file(&path.join(file.to_string()), value)?;
+        }
+        OpenOptions::new()
+            .write(true)
+            .open(path.join("cgroup.procs"))
+    };
+    configure().inspect_err(|_| {
+        let _ = std::fs::remove_dir(path);
+    })
+}
+
+impl Cgroup {
+    /// Creates the cgroup of the command, None when the task has no `cgroup` option
+    pub fn create(execcfg: &BestExecSettings) -> SrResult<Option<Cgroup>> {
+        let Some(limits) = &execcfg.cgroup else {
+            return Ok(None);
+        };
+        for (file, value) in limits {
+            file.check(value).map_err(|e| {
+                error!("{}", e);
+                SrError::ConfigurationError
+            })?;
+        }
+        let parents = [
+            CGROUP_BASE,
+            component(&execcfg.role)?,
+            component(execcfg.task.as_deref().unwrap_or_default())?,
+        ];
+        let controllers = controllers(limits);
+        with_privileges(&[Cap::DAC_OVERRIDE], || {
+            let mut path = PathBuf::from(CGROUP_ROOT);
+            for parent in parents {
+                if !controllers.is_empty() {
+                    write_file(&path.join("cgroup.subtree_control"), &controllers)?;
+                }
+                path.push(parent);
+                match std::fs::create_dir(&path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(e),
+                    _ => {}
+                }
+            }
+            if !controllers.is_empty() {
+                write_file(&path.join("cgroup.subtree_control"), &controllers)?;
+            }
+            path.push(std::process::id().to_string());
+            let procs = create_leaf(&path, limits)?;
+            Ok(Cgroup { path, procs })
+        })
+        .map(Some)
+        .map_err(|e| {
+            error!("Unable to create the cgroup of the command: {}", e);
+            SrError::SystemError
+        })
+    }
+
+    /// File descriptor used by the command to join the cgroup, see `join`
+    pub fn procs(&self) -> std::io::Result<File> {
+        self.procs.try_clone()
+    }
+}
+
+/// Moves the calling process to the cgroup, called before exec
+pub fn join(procs: &File) -> std::io::Result<()> {
+    let mut procs = procs;
+    procs.write_all(b"0")
+}
+
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        // fails when processes of the command are still running, e.g. daemons
+        if let Err(e) = with_privileges(&[Cap::DAC_OVERRIDE], || std::fs::remove_dir(&self.path)) {
+            warn!("Unable to remove the cgroup {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rar_common::database::options::SCgroupFile;
+
+    use super::*;
+
+    #[test]
+    fn test_component() {
+        assert_eq!(component("r1"), Ok("r1"));
+        assert_eq!(component(".."), Err(SrError::ConfigurationError));
+        assert_eq!(component("a/b"), Err(SrError::ConfigurationError));
+        assert_eq!(component(""), Err(SrError::ConfigurationError));
+    }
+
+    #[test]
+    fn test_controllers() {
+        assert_eq!(controllers(&SCgroup::new()), "");
+        let limits = SCgroup::from([
+            (SCgroupFile::PidsMax, "100".to_string()),
+            (SCgroupFile::MemoryMax, "512M".to_string()),
+        ]);
+        assert_eq!(controllers(&limits), "+memory +pids");
+    }
+
+    #[test]
+    fn test_create_without_option() {
+        let execcfg = BestExecSettings::default();
+        assert!(Cgroup::create(&execcfg).unwrap().is_none());
+    }
+}
@@ -0,0 +1,315 @@
+//! Privileged file editing, `dosr --edit FILE...`
+//! The files are read with the task credentials and copied to a temporary directory owned by the user,
+//! the editor of the user runs without privileges, and changed files are written back with the task credentials.
+//! Symbolic links, hard links, and files in directories writable by the user are refused.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    os::{
+        fd::OwnedFd,
+        unix::fs::{MetadataExt, OpenOptionsExt},
+    },
+    path::{Component, Path, PathBuf},
+    process::Command,
+};
+
+use capctl::CapState;
+use log::{debug, error, info};
+use nix::{
+    sys::wait::{waitpid, WaitStatus},
+    unistd::{fork, mkdtemp, pipe, ForkResult},
+};
+use rar_common::Cred;
+
+use crate::{
+    error::{SrError, SrResult},
+    finder::BestExecSettings,
+    set_capabilities, setuid_setgid,
+};
+
+const DEFAULT_EDITOR: &str = "vi";
+const EDITOR_VARIABLES: [&str; 3] = ["SUDO_EDITOR", "VISUAL", "EDITOR"];
+
+/// Makes `path` absolute without resolving symbolic links, `..` components are refused
+pub fn absolute(path: &Path) -> SrResult<PathBuf> {
+    if path.components().any(|c| c == Component::ParentDir) {
+        error!("{}: '..' is not allowed in edited paths", path.display());
+        return Err(SrError::InvalidAgruments);
+    }
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    Ok(path.components().collect())
+}
+
+/// Whether the user may write to the directory, then replace its entries.
+/// root may write anywhere, so it is not checked.
+fn writable_by(meta: &std::fs::Metadata, user: &Cred) -> bool {
+    !user.user.uid.is_root()
+        && (meta.uid() == user.user.uid.as_raw()
+            || meta.mode() & 0o002 != 0
+            || (meta.mode() & 0o020 != 0
+                && (meta.gid() == user.user.gid.as_raw()
+                    || user.groups.iter().any(|g| g.gid.as_raw() == meta.gid()))))
+}
+
+/// Refuses paths with a symbolic link, or with a parent directory writable by the user
+fn check_path(path: &Path, user: &Cred) -> std::io::Result<()> {
+    let denied = |reason: &str| {
+        std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("{}: {}", path.display(), reason),
+        )
+    };
+    for ancestor in path.ancestors().skip(1) {
+        let meta = std::fs::symlink_metadata(ancestor)?;
+        if meta.file_type().is_symlink() {
+            return Err(denied("a parent directory is a symbolic link"));
+        }
+        if writable_by(&meta, user) {
+            return Err(denied("a parent directory is writable by the user"));
+        }
+    }
+    Ok(())
+}
+
+/// A hard linked file could be modified through another path
+fn check_file(path: &Path, file: &File) -> std::io::Result<()> {
+    let meta = file.metadata()?;
+    if !meta.is_file() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{}: not a regular file", path.display()),
+        ));
+    }
+    if meta.nlink() > 1 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("{}: the file has several hard links", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Reads a file, None when it does not exist yet
+fn read_file(path: &Path, user: &Cred) -> std::io::Result<Option<Vec<u8>>> {
+    check_path(path, user)?;
+    let mut file = match OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK)
+        .open(path)
+    {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    check_file(path, &file)?;
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)?;
+    Ok(Some(content))
+}
+
+/// Writes a file back, a file that did not exist must still not exist
+fn write_file(path: &Path, user: &Cred, content: &[u8], existed: bool) -> std::io::Result<()> {
+    check_path(path, user)?;
+    let mut options = OpenOptions::new();
+    options
+        .write(true)
+        .mode(0o644)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK);
+    if !existed {
+        options.create_new(true);
+    }
+    let mut file = options.open(path)?;
+    check_file(path, &file)?;
+    file.set_len(0)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+/// Runs `f` in a child process with the task credentials, its output is returned to the parent.
+/// The child exits without returning, its errors are reported before.
+fn as_target<F>(execcfg: &BestExecSettings, f: F) -> SrResult<Vec<u8>>
+where
+    F: FnOnce() -> std::io::Result<Vec<u8>>,
+{
+    let (reader, writer) = pipe().map_err(|_| SrError::SystemError)?;
+    match unsafe { fork() }.map_err(|_| SrError::SystemError)? {
+        ForkResult::Child => {
+            drop(reader);
+            let code = match target_child(execcfg, f, writer) {
+                Ok(()) => 0,
+                Err(e) => {
+                    eprintln!("dosr: {}", e);
+                    1
+                }
+            };
+            unsafe { libc::_exit(code) };
+        }
+        ForkResult::Parent { child } => {
+            drop(writer);
+            let mut output = Vec::new();
+            File::from(reader).read_to_end(&mut output)?;
+            match waitpid(child, None) {
+                Ok(WaitStatus::Exited(_, 0)) => Ok(output),
+                _ => Err(SrError::PermissionDenied),
+            }
+        }
+    }
+}
+
+fn target_child<F>(execcfg: &BestExecSettings, f: F, writer: OwnedFd) -> std::io::Result<()>
+where
+    F: FnOnce() -> std::io::Result<Vec<u8>>,
+{
+    setuid_setgid(execcfg).map_err(|e| std::io::Error::other(e.to_string()))?;
+    set_capabilities(execcfg).map_err(|e| std::io::Error::other(e.to_string()))?;
+    let mut state = CapState::get_current()?;
+    state.effective = state.permitted & execcfg.cred.caps.unwrap_or_default();
+    state.set_current()?;
+    let output = f()?;
+    File::from(writer).write_all(&output)
+}
+
+fn editor() -> Vec<String> {
+    EDITOR_VARIABLES
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find_map(|editor| shell_words::split(&editor).ok().filter(|e| !e.is_empty()))
+        .unwrap_or_else(|| vec![DEFAULT_EDITOR.to_string()])
+}
+
+/// Name of the temporary copy, the file name is kept for the editor syntax highlighting
+fn temp_name(index: usize, path: &Path) -> String {
+    format!(
+        "{}-{}",
+        index,
+        path.file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default()
+    )
+}
+
+/// Edits `files` (absolute paths) as allowed by the task
+pub fn edit(execcfg: &BestExecSettings, user: &Cred, files: &[PathBuf]) -> SrResult<()> {
+    let output = as_target(execcfg, || {
+        let contents = files
+            .iter()
+            .map(|path| read_file(path, user))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        cbor4ii::serde::to_vec(Vec::new(), &contents).map_err(std::io::Error::other)
+    })?;
+    let originals: Vec<Option<Vec<u8>>> = cbor4ii::serde::from_slice(&output)?;
+    // the temporary directory belongs to the user, dosr runs with its uid
+    let dir = mkdtemp(&std::env::temp_dir().join("dosr-edit.XXXXXX")).map_err(|e| {
+        error!("Unable to create a temporary directory: {}", e);
+        SrError::SystemError
+    })?;
+    let result = edit_copies(execcfg, user, files, &originals, &dir);
+    if let Err(e) = std::fs::remove_dir_all(&dir) {
+        error!("Unable to remove {}: {}", dir.display(), e);
+    }
+    result
+}
+
+fn edit_copies(
+    execcfg: &BestExecSettings,
+    user: &Cred,
+    files: &[PathBuf],
+    originals: &[Option<Vec<u8>>],
+    dir: &Path,
+) -> SrResult<()> {
+    let copies: Vec<PathBuf> = files
+        .iter()
+        .enumerate()
+        .map(|(i, path)| dir.join(temp_name(i, path)))
+        .collect();
+    for (copy, original) in copies.iter().zip(originals) {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(copy)?
+            .write_all(original.as_deref().unwrap_or_default())?;
+    }
+    let editor = editor();
+    debug!("Running editor {:?}", editor);
+    let status = Command::new(&editor[0])
+        .args(&editor[1..])
+        .args(&copies)
+        .status()
+        .inspect_err(|e| error!("Unable to run the editor {}: {}", editor[0], e))?;
+    if !status.success() {
+        eprintln!("dosr: the editor failed, the files are left unchanged");
+        return Err(SrError::ExecutionFailed);
+    }
+    let mut changed = Vec::new();
+    for ((path, copy), original) in files.iter().zip(&copies).zip(originals) {
+        let content = std::fs::read(copy)?;
+        if original.as_deref() == Some(content.as_slice())
+            || (original.is_none() && content.is_empty())
+        {
+            info!("{} unchanged", path.display());
+        } else {
+            changed.push((path, content, original.is_some()));
+        }
+    }
+    if changed.is_empty() {
+        return Ok(());
+    }
+    as_target(execcfg, || {
+        for (path, content, existed) in &changed {
+            write_file(path, user, content, *existed)?;
+            info!("{} written", path.display());
+        }
+        Ok(Vec::new())
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use nix::unistd::User;
+
+    use super::*;
+
+    #[test]
+    fn test_absolute() {
+        assert_eq!(
+            absolute(Path::new("/etc//nginx/./nginx.conf")),
+            Ok(PathBuf::from("/etc/nginx/nginx.conf"))
+        );
+        assert_eq!(
+            absolute(Path::new("/etc/../etc/shadow")),
+            Err(SrError::InvalidAgruments)
+        );
+        assert!(absolute(Path::new("nginx.conf")).unwrap().is_absolute());
+    }
+
+    #[test]
+    fn test_read_write_file() {
+        let user = Cred::builder().build();
+        let dir = mkdtemp(&std::env::temp_dir().join("dosr-test.XXXXXX")).unwrap();
+        let path = dir.join("file.conf");
+        // the temporary directory is world writable
+        let nobody = Cred {
+            user: User::from_name("nobody").unwrap().unwrap(),
+            groups: Vec::new(),
+            tty: None,
+            ppid: nix::unistd::getppid(),
+        };
+        assert!(read_file(&dir.join("tmp.conf"), &nobody).is_err());
+        assert!(check_path(Path::new("/etc/passwd"), &nobody).is_ok());
+        assert_eq!(read_file(&path, &user).unwrap(), None);
+        std::fs::write(&path, "content").unwrap();
+        let file = File::open(&path).unwrap();
+        assert!(check_file(&path, &file).is_ok());
+        std::fs::hard_link(&path, dir.join("link")).unwrap();
+        assert!(check_file(&path, &file).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(temp_name(1, Path::new("/etc/nginx.conf")), "1-nginx.conf");
+    }
+}
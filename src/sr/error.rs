@@ -3,7 +3,7 @@ use std::{
     process::{ExitCode, Termination},
 };
 
-use libc::{EACCES, EFAULT, EINVAL, ENOENT, EPERM};
+use libc::{EACCES, EAGAIN, EFAULT, EINVAL};
 
 /// Critical security program errors with minimal information exposure
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -12,6 +12,8 @@ pub enum SrError {
     InvalidAgruments,
     /// Authentication failed
     AuthenticationFailed,
+    /// Authentication needs a prompt, but prompting is disabled
+    InteractionRequired,
     /// Configuration error
     ConfigurationError,
     /// Insufficient privileges to execute the program
@@ -20,21 +22,32 @@ pub enum SrError {
     PermissionDenied,
     /// Command execution failed
     ExecutionFailed,
+    /// The command was not found
+    CommandNotFound,
     /// Internal system error
     SystemError,
 }
 
+/// EX_NOPERM of sysexits.h, seldom used by commands unlike EPERM
+const EXIT_DENIED: u8 = 77;
+/// Like a shell, when the command cannot be executed
+const EXIT_NOT_EXECUTABLE: u8 = 126;
+/// Like a shell, when the command is not found
+const EXIT_NOT_FOUND: u8 = 127;
+
 impl Termination for SrError {
     fn report(self) -> ExitCode {
         ExitCode::from(match self {
-            Self::InvalidAgruments => EINVAL,
-            Self::AuthenticationFailed => EACCES,
-            Self::ConfigurationError => EINVAL,
-            Self::InsufficientPrivileges => EACCES,
-            Self::ExecutionFailed => ENOENT,
-            Self::SystemError => EFAULT,
-            Self::PermissionDenied => EPERM,
-        } as u8)
+            Self::InvalidAgruments => EINVAL as u8,
+            Self::AuthenticationFailed => EACCES as u8,
+            Self::InteractionRequired => EAGAIN as u8,
+            Self::ConfigurationError => EINVAL as u8,
+            Self::InsufficientPrivileges => EACCES as u8,
+            Self::ExecutionFailed => EXIT_NOT_EXECUTABLE,
+            Self::CommandNotFound => EXIT_NOT_FOUND,
+            Self::SystemError => EFAULT as u8,
+            Self::PermissionDenied => EXIT_DENIED,
+        })
     }
 }
 
@@ -43,10 +56,12 @@ impl fmt::Display for SrError {
         // Minimal, non-revealing error messages
         let msg = match self {
             Self::AuthenticationFailed => "Authentication failed",
+            Self::InteractionRequired => "Interaction required",
             Self::ConfigurationError => "Configuration error",
             Self::InsufficientPrivileges => "Insufficient privileges",
             Self::PermissionDenied => "Permission denied",
             Self::ExecutionFailed => "Execution failed",
+            Self::CommandNotFound => "Command not found",
             Self::SystemError => "System error",
             Self::InvalidAgruments => "Invalid arguments",
         };
@@ -106,6 +121,10 @@ mod tests {
             SrError::AuthenticationFailed.to_string(),
             "Authentication failed"
         );
+        assert_eq!(
+            SrError::InteractionRequired.to_string(),
+            "Interaction required"
+        );
         assert_eq!(
             SrError::ConfigurationError.to_string(),
             "Configuration error"
@@ -116,6 +135,7 @@ mod tests {
         );
         assert_eq!(SrError::PermissionDenied.to_string(), "Permission denied");
         assert_eq!(SrError::ExecutionFailed.to_string(), "Execution failed");
+        assert_eq!(SrError::CommandNotFound.to_string(), "Command not found");
         assert_eq!(SrError::SystemError.to_string(), "System error");
         assert_eq!(SrError::InvalidAgruments.to_string(), "Invalid arguments");
     }
@@ -147,6 +167,10 @@ mod tests {
             SrError::AuthenticationFailed.report(),
             ExitCode::from(EACCES as u8)
         );
+        assert_eq!(
+            SrError::InteractionRequired.report(),
+            ExitCode::from(EAGAIN as u8)
+        );
         assert_eq!(
             SrError::ConfigurationError.report(),
             ExitCode::from(EINVAL as u8)
@@ -157,12 +181,16 @@ mod tests {
         );
         assert_eq!(
             SrError::ExecutionFailed.report(),
-            ExitCode::from(ENOENT as u8)
+            ExitCode::from(EXIT_NOT_EXECUTABLE)
+        );
+        assert_eq!(
+            SrError::CommandNotFound.report(),
+            ExitCode::from(EXIT_NOT_FOUND)
         );
         assert_eq!(SrError::SystemError.report(), ExitCode::from(EFAULT as u8));
         assert_eq!(
             SrError::PermissionDenied.report(),
-            ExitCode::from(EPERM as u8)
+            ExitCode::from(EXIT_DENIED)
         );
     }
 
@@ -0,0 +1,97 @@
+//! Execution of the command by dosr itself, at the end of the pre-exec hook.
+//! The seccomp filters of `noexec` and `intercept` single out the exec of the command by the address
+//! of its path in dosr, the exec replaces the address space, so the execve calls of the command don't match.
+//! Programs able to run arbitrary code, like interpreters, may still map this address.
+
+use std::{
+    collections::HashMap,
+    ffi::{CString, OsStr},
+    os::unix::ffi::OsStrExt,
+    path::Path,
+};
+
+use log::error;
+
+use crate::error::{SrError, SrResult};
+
+pub struct Exec {
+    path: CString,
+    argv: Vec<CString>,
+    envp: Vec<CString>,
+}
+
+fn cstring(value: &[u8]) -> SrResult<CString> {
+    CString::new(value).map_err(|_| {
+        error!("Unexpected NUL byte in {}", String::from_utf8_lossy(value));
+        SrError::InvalidAgruments
+    })
+}
+
+impl Exec {
+    /// Prepares the exec of the command, argv[0] is `arg0` or the command path like `Command` does
+    pub fn new(
+        path: &Path,
+        arg0: Option<&str>,
+        args: &[String],
+        env: &HashMap<String, String>,
+    ) -> SrResult<Exec> {
+        let path = cstring(path.as_os_str().as_bytes())?;
+        let argv = std::iter::once(
+            arg0.map(|arg0| cstring(arg0.as_bytes()))
+                .unwrap_or_else(|| Ok(path.clone())),
+        )
+        .chain(args.iter().map(|arg| cstring(OsStr::new(arg).as_bytes())))
+        .collect::<SrResult<Vec<_>>>()?;
+        let envp = env
+            .iter()
+            .map(|(key, value)| cstring(format!("{}={}", key, value).as_bytes()))
+            .collect::<SrResult<Vec<_>>>()?;
+        Ok(Exec { path, argv, envp })
+    }
+
+    /// The path argument of the execve call of the command
+    pub fn path_address(&self) -> u64 {
+        self.path.as_ptr() as usize as u64
+    }
+
+    /// Executes the command, returns only on failure
+    pub fn exec(&self) -> std::io::Error {
+        let pointers = |strings: &[CString]| {
+            strings
+                .iter()
+                .map(|s| s.as_ptr())
+                .chain(std::iter::once(std::ptr::null()))
+                .collect::<Vec<_>>()
+        };
+        let argv = pointers(&self.argv);
+        let envp = pointers(&self.envp);
+        unsafe { libc::execve(self.path.as_ptr(), argv.as_ptr(), envp.as_ptr()) };
+        std::io::Error::last_os_error()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exec_new() {
+        let env = HashMap::from([("PATH".to_string(), "/usr/bin".to_string())]);
+        let exec = Exec::new(
+            Path::new("/usr/bin/less"),
+            None,
+            &["file".to_string()],
+            &env,
+        )
+        .unwrap();
+        assert_eq!(
+            exec.argv,
+            vec![CString::from(c"/usr/bin/less"), CString::from(c"file")]
+        );
+        assert_eq!(exec.envp, vec![CString::from(c"PATH=/usr/bin")]);
+        assert_eq!(exec.path_address(), exec.path.as_ptr() as u64);
+        let exec = Exec::new(Path::new("/bin/bash"), Some("-bash"), &[], &env).unwrap();
+        assert_eq!(exec.argv, vec![CString::from(c"-bash")]);
+        assert!(Exec::new(Path::new("/bin/ls"), None, &["a\0b".to_string()], &env).is_err());
+    }
+}
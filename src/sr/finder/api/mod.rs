@@ -19,6 +19,8 @@ mod hashchecker;
 mod hierarchy;
 #[cfg(feature = "landlock")]
 mod landlock;
+#[cfg(feature = "seccomp")]
+mod seccomp;
 #[cfg(feature = "ssd")]
 mod ssd;
 
@@ -129,7 +131,7 @@ impl Api {
     }
 }
 
-pub(super) fn register_plugins() {
+pub(crate) fn register_plugins() {
     #[cfg(feature = "ssd")]
     ssd::register();
     #[cfg(feature = "hashchecker")]
@@ -138,4 +140,6 @@ pub(super) fn register_plugins() {
     hierarchy::register();
     #[cfg(feature = "landlock")]
     landlock::register();
+    #[cfg(feature = "seccomp")]
+    seccomp::register();
 }
@@ -0,0 +1,198 @@
+//! Seccomp syscall filtering of the command, from the `seccomp` field of the task credentials.
+//! Without `allow`, every syscall is permitted but the `deny` ones; with `allow`, only the listed
+//! syscalls (and `execve`, to start the command) are permitted. Filtered syscalls get the `default` action.
+//! Lists accept syscall names and presets.
+use std::collections::BTreeSet;
+
+use libseccomp::{ScmpAction, ScmpFilterContext, ScmpSyscall};
+use log::{debug, error};
+use serde::Deserialize;
+
+use crate::{
+    error::{SrError, SrResult},
+    finder::api::{Api, ApiEvent, EventKey},
+};
+
+const PRESETS: [(&str, &[&str]); 3] = [
+    (
+        "no-kernel-modules",
+        &[
+            "init_module",
+            "finit_module",
+            "delete_module",
+            "kexec_load",
+            "kexec_file_load",
+        ],
+    ),
+    (
+        "no-ptrace",
+        &["ptrace", "process_vm_readv", "process_vm_writev"],
+    ),
+    (
+        "no-mount",
+        &[
+            "mount",
+            "umount2",
+            "pivot_root",
+            "move_mount",
+            "open_tree",
+            "fsopen",
+            "fsconfig",
+            "fsmount",
+            "fspick",
+            "mount_setattr",
+        ],
+    ),
+];
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum SeccompAction {
+    /// The syscall fails with EPERM
+    #[default]
+    Errno,
+    Kill,
+    /// The syscall is allowed and logged by the kernel audit
+    Log,
+}
+
+impl From<SeccompAction> for ScmpAction {
+    fn from(action: SeccompAction) -> Self {
+        match action {
+            SeccompAction::Errno => ScmpAction::Errno(libc::EPERM),
+            SeccompAction::Kill => ScmpAction::KillProcess,
+            SeccompAction::Log => ScmpAction::Log,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct SeccompPolicy {
+    #[serde(default)]
+    default: SeccompAction,
+    #[serde(default)]
+    allow: Vec<String>,
+    #[serde(default)]
+    deny: Vec<String>,
+}
+
+/// Replaces the presets by their syscalls
+fn expand(names: &[String]) -> BTreeSet<&str> {
+    let mut syscalls = BTreeSet::new();
+    for name in names {
+        match PRESETS.iter().find(|(preset, _)| preset == name) {
+            Some((_, preset)) => syscalls.extend(preset.iter().copied()),
+            None => {
+                syscalls.insert(name.as_str());
+            }
+        }
+    }
+    syscalls
+}
+
+fn resolve(name: &str) -> SrResult<ScmpSyscall> {
+    ScmpSyscall::from_name(name).map_err(|_| {
+        error!("Unknown syscall {} in the seccomp policy", name);
+        SrError::ConfigurationError
+    })
+}
+
+fn build_filter(policy: &SeccompPolicy) -> SrResult<ScmpFilterContext> {
+    let action = ScmpAction::from(policy.default);
+    let deny = expand(&policy.deny);
+    let (default, rules) = if policy.allow.is_empty() {
+        (
+            ScmpAction::Allow,
+            deny.into_iter().map(|s| (action, s)).collect(),
+        )
+    } else {
+        let mut allow = expand(&policy.allow);
+        allow.insert("execve");
+        (
+            action,
+            allow
+                .difference(&deny)
+                .map(|s| (ScmpAction::Allow, *s))
+                .collect::<Vec<_>>(),
+        )
+    };
+    let mut ctx = ScmpFilterContext::new(default).map_err(|e| {
+        error!("Unable to create the seccomp filter: {}", e);
+        SrError::SystemError
+    })?;
+    for (action, name) in rules {
+        debug!("seccomp {:?} {}", action, name);
+        ctx.add_rule(action, resolve(name)?).map_err(|e| {
+            error!("Unable to add the seccomp rule for {}: {}", name, e);
+            SrError::ConfigurationError
+        })?;
+    }
+    Ok(ctx)
+}
+
+fn pre_exec(event: &mut ApiEvent) -> SrResult<()> {
+    if let ApiEvent::PreExec(_, settings) = event {
+        if let Some(value) = settings.cred.extra_values.get("seccomp") {
+            let policy: SeccompPolicy = serde_json::from_value(value.clone()).map_err(|e| {
+                error!("Invalid seccomp policy: {}", e);
+                SrError::ConfigurationError
+            })?;
+            build_filter(&policy)?.load().map_err(|e| {
+                error!("Unable to load the seccomp filter: {}", e);
+                SrError::SystemError
+            })?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn register() {
+    Api::register(EventKey::PreExec, pre_exec);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_seccomp_policy_serde() {
+        let policy: SeccompPolicy = serde_json::from_value(json!({
+            "default": "kill",
+            "deny": ["no-ptrace", "keyctl"]
+        }))
+        .unwrap();
+        assert_eq!(policy.default, SeccompAction::Kill);
+        assert!(policy.allow.is_empty());
+        assert_eq!(
+            expand(&policy.deny),
+            BTreeSet::from(["keyctl", "process_vm_readv", "process_vm_writev", "ptrace"])
+        );
+        assert!(serde_json::from_value::<SeccompPolicy>(json!({"default": "trap"})).is_err());
+        assert!(serde_json::from_value::<SeccompPolicy>(json!({"denied": []})).is_err());
+    }
+
+    #[test]
+    fn test_build_filter() {
+        let policy = SeccompPolicy {
+            deny: vec!["no-mount".to_string(), "no-kernel-modules".to_string()],
+            ..Default::default()
+        };
+        assert!(build_filter(&policy).is_ok());
+        let policy = SeccompPolicy {
+            allow: vec!["read".to_string(), "write".to_string()],
+            deny: vec!["write".to_string()],
+            ..Default::default()
+        };
+        assert!(build_filter(&policy).is_ok());
+        let policy = SeccompPolicy {
+            deny: vec!["not_a_syscall".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            build_filter(&policy).err(),
+            Some(SrError::ConfigurationError)
+        );
+    }
+}
@@ -1,9 +1,17 @@
 use log::{debug, info, warn};
 use rar_common::{
-    database::score::{CmdMin, CmdOrder},
+    database::{
+        score::{CmdMin, CmdOrder},
+        structs::{SCommand, SCommands, SetBehavior},
+    },
     util::{all_paths_from_env, match_single_path},
 };
-use std::path::PathBuf;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::finder::api::{Api, ApiEvent};
+
+const EDIT_KEY: &str = "edit";
 
 fn match_path(
     env_path: &[&str],
@@ -145,6 +153,61 @@ fn match_command_line(
     result
 }
 
+/// Edit commands are objects with an `edit` list of path patterns: `{"edit": ["/etc/nginx/*.conf"]}`
+pub fn is_edit_command(value: &Value) -> bool {
+    value.get(EDIT_KEY).is_some()
+}
+
+/// Every edited file must match one of the path patterns of the edit command
+pub fn evaluate_edit_match(first: &Path, others: &[String], value: &Value) -> CmdMin {
+    let patterns = match value.get(EDIT_KEY) {
+        Some(Value::Array(patterns)) => patterns.iter().filter_map(Value::as_str).collect(),
+        Some(Value::String(pattern)) => vec![pattern.as_str()],
+        _ => {
+            warn!("Invalid edit command: {}", value);
+            return CmdMin::empty();
+        }
+    };
+    let mut result = CmdMin::MATCH;
+    for file in std::iter::once(first).chain(others.iter().map(Path::new)) {
+        let min = patterns
+            .iter()
+            .map(|pattern| match_edit_path(file, pattern))
+            .filter(CmdMin::matching)
+            .min_by_key(|min| min.order)
+            .unwrap_or_default();
+        if !min.matching() {
+            debug!("evaluate_edit_match: {} is not allowed", file.display());
+            return CmdMin::empty();
+        }
+        result.union_order(min.order);
+    }
+    result
+}
+
+fn match_edit_path(file: &Path, pattern: &str) -> CmdMin {
+    if file == Path::new(pattern) {
+        return CmdMin::MATCH;
+    }
+    #[cfg(feature = "glob")]
+    if glob::Pattern::new(pattern).is_ok_and(|p| {
+        p.matches_path_with(
+            file,
+            glob::MatchOptions {
+                require_literal_separator: true,
+                require_literal_leading_dot: true,
+                ..Default::default()
+            },
+        )
+    }) {
+        return CmdMin::builder()
+            .matching()
+            .order(CmdOrder::WildcardPath)
+            .build();
+    }
+    CmdMin::empty()
+}
+
 #[inline(always)]
 pub fn evaluate_command_match(
     env_path: &[&str],
@@ -170,12 +233,85 @@ pub fn evaluate_command_match(
     }
 }
 
+/// Whether the commands of a task allow the command line, like the finder:
+/// a `del` command denies it, otherwise `default: all` or an `add` command allows it.
+/// Used to check each exec of the command with the `intercept` option.
+pub fn is_command_allowed(
+    commands: &SCommands,
+    env_path: &[&str],
+    cmd_path: &PathBuf,
+    cmd_args: &[String],
+) -> bool {
+    let matching = |command: &SCommand| match command {
+        SCommand::Simple(role_cmd) => evaluate_command_match(
+            env_path,
+            cmd_path,
+            cmd_args,
+            role_cmd,
+            &CmdMin::empty(),
+            &mut None,
+        )
+        .matching(),
+        SCommand::Complex(value) if is_edit_command(value) => false,
+        SCommand::Complex(value) => {
+            let mut cmd_min = CmdMin::empty();
+            let mut final_path = None;
+            Api::notify(ApiEvent::ProcessComplexCommand(
+                value,
+                env_path,
+                cmd_path,
+                cmd_args,
+                &mut cmd_min,
+                &mut final_path,
+            ))
+            .is_ok()
+                && cmd_min.matching()
+        }
+    };
+    !commands.sub.iter().any(matching)
+        && (commands.default == Some(SetBehavior::All) || commands.add.iter().any(matching))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use serde_json::json;
     use test_log::test;
 
+    #[test]
+    fn test_evaluate_edit_match() {
+        let value = json!({"edit": ["/etc/nginx/nginx.conf", "/etc/nginx/conf.d/*.conf"]});
+        assert!(is_edit_command(&value));
+        assert!(!is_edit_command(&json!({"command": "/usr/bin/ls"})));
+        assert_eq!(
+            evaluate_edit_match(Path::new("/etc/nginx/nginx.conf"), &[], &value),
+            CmdMin::MATCH
+        );
+        assert_eq!(
+            evaluate_edit_match(
+                Path::new("/etc/nginx/nginx.conf"),
+                &["/etc/nginx/conf.d/site.conf".to_string()],
+                &value
+            ),
+            CmdMin::builder()
+                .matching()
+                .order(CmdOrder::WildcardPath)
+                .build()
+        );
+        // wildcards do not cross directories, and every file must match
+        assert!(
+            !evaluate_edit_match(Path::new("/etc/nginx/conf.d/sub/site.conf"), &[], &value)
+                .matching()
+        );
+        assert!(!evaluate_edit_match(
+            Path::new("/etc/nginx/nginx.conf"),
+            &["/etc/shadow".to_string()],
+            &value
+        )
+        .matching());
+    }
+
     #[test]
     fn test_match_path_full_wildcard() {
         let env_path = ["/usr/bin", "/bin"];
@@ -593,4 +729,33 @@ mod tests {
         );
         assert_eq!(final_path, None);
     }
+
+    #[test]
+    fn test_is_command_allowed() {
+        let env_path = ["/usr/bin", "/bin"];
+        let ls = PathBuf::from("/usr/bin/ls");
+        let commands = SCommands {
+            add: vec![SCommand::Simple("/usr/bin/ls -l".to_string())],
+            sub: vec![SCommand::Simple("/usr/bin/sh".to_string())],
+            ..Default::default()
+        };
+        assert!(is_command_allowed(
+            &commands,
+            &env_path,
+            &ls,
+            &["-l".to_string()]
+        ));
+        assert!(!is_command_allowed(&commands, &env_path, &ls, &[]));
+        let commands = SCommands {
+            default: Some(SetBehavior::All),
+            ..commands
+        };
+        assert!(is_command_allowed(&commands, &env_path, &ls, &[]));
+        assert!(!is_command_allowed(
+            &commands,
+            &env_path,
+            &PathBuf::from("/usr/bin/sh"),
+            &[]
+        ));
+    }
 }
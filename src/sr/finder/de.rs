@@ -15,7 +15,7 @@ use rar_common::{
         score::{
             ActorMatchMin, CapsMin, CmdMin, Score, SecurityMin, SetgidMin, SetuidMin, TaskScore,
         },
-        structs::{SCapabilities, SetBehavior},
+        structs::{SCapabilities, SCommand, SCommands, SetBehavior},
     },
     util::capabilities_are_exploitable,
     Cred,
@@ -80,6 +80,7 @@ impl Display for IdTask<'_> {
 #[derivative(PartialEq, Eq)]
 pub struct DTaskFinder<'a> {
     pub id: IdTask<'a>,
+    pub purpose: Option<Cow<'a, str>>,
     #[builder(default)]
     pub score: TaskScore,
     pub cred: CredData<'a>,
@@ -522,6 +523,8 @@ impl<'de: 'a, 'a> DeserializeSeed<'de> for TaskFinderDeserializer<'a, '_> {
         enum Field<'a> {
             #[serde(alias = "n")]
             Name,
+            #[serde(alias = "p")]
+            Purpose,
             #[serde(alias = "i", alias = "credentials")]
             Cred,
             #[serde(alias = "c", alias = "cmds")]
@@ -553,6 +556,7 @@ impl<'de: 'a, 'a> DeserializeSeed<'de> for TaskFinderDeserializer<'a, '_> {
             {
                 // Use local temporaries for each field
                 let mut id = IdTask::Number(self.i);
+                let mut purpose = None;
                 let mut score = TaskScore::default();
                 let mut commands = None;
                 let mut options = None;
@@ -569,7 +573,9 @@ impl<'de: 'a, 'a> DeserializeSeed<'de> for TaskFinderDeserializer<'a, '_> {
                             if let Some(path) = opt.path.as_ref() {
                                 self.spath.union(path.clone());
                             }
-                            if self.cli.info && opt.execinfo.is_some_and(|i| i.is_hide()) {
+                            if (self.cli.info || self.cli.list || self.cli.explain)
+                                && opt.execinfo.is_some_and(|i| i.is_hide())
+                            {
                                 while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
                                 return Ok(None);
                             }
@@ -605,6 +611,10 @@ impl<'de: 'a, 'a> DeserializeSeed<'de> for TaskFinderDeserializer<'a, '_> {
                             }
                             id = task_name;
                         }
+                        Field::Purpose => {
+                            debug!("TaskFinderVisitor: purpose");
+                            purpose = Some(map.next_value()?);
+                        }
                         Field::Cred => {
                             debug!("TaskFinderVisitor: cred");
                             let result = map
@@ -619,9 +629,9 @@ impl<'de: 'a, 'a> DeserializeSeed<'de> for TaskFinderDeserializer<'a, '_> {
                         }
                         Field::Commands => {
                             debug!("TaskFinderVisitor: commands");
-                            // if is_human_readable -> next_value
+                            // if is_human_readable, listing or editing -> next_value
                             // else -> next_value_seed -> no memory allocation, just the result, thus highly optimizing
-                            if self.human_readable {
+                            if self.human_readable || self.cli.list || self.cli.edit {
                                 commands = Some(map.next_value()?);
                             } else {
                                 map.next_value_seed(DCommandListDeserializer {
@@ -644,6 +654,7 @@ impl<'de: 'a, 'a> DeserializeSeed<'de> for TaskFinderDeserializer<'a, '_> {
                 debug!("TaskFinderVisitor: final_path {:?}", final_path);
                 Ok(Some(DTaskFinder {
                     id,
+                    purpose,
                     score,
                     cred,
                     commands,
@@ -653,7 +664,7 @@ impl<'de: 'a, 'a> DeserializeSeed<'de> for TaskFinderDeserializer<'a, '_> {
             }
         }
 
-        const FIELDS: &[&str] = &["name", "cred", "commands", "options"];
+        const FIELDS: &[&str] = &["name", "purpose", "cred", "commands", "options"];
         let human_readable = deserializer.is_human_readable();
         deserializer.deserialize_struct(
             "STask",
@@ -1235,6 +1246,26 @@ pub struct DCommandList<'a> {
     pub del: Cow<'a, [DCommand<'a>]>,
 }
 
+impl From<&DCommand<'_>> for SCommand {
+    fn from(command: &DCommand<'_>) -> Self {
+        match command {
+            DCommand::Simple(command) => SCommand::Simple(command.to_string()),
+            DCommand::Complex(value) => SCommand::Complex(value.clone()),
+        }
+    }
+}
+
+impl From<&DCommandList<'_>> for SCommands {
+    fn from(commands: &DCommandList<'_>) -> Self {
+        SCommands {
+            default: commands.default_behavior,
+            add: commands.add.iter().map(SCommand::from).collect(),
+            sub: commands.del.iter().map(SCommand::from).collect(),
+            _extra_fields: Default::default(),
+        }
+    }
+}
+
 impl<'de: 'a, 'a> Deserialize<'de> for DCommandList<'a> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -1526,13 +1557,18 @@ impl<'de: 'a, 'a> DeserializeSeed<'de> for DCommandDeserializer<'a> {
                 while let Some((key, value)) = map.next_entry::<&str, Value>()? {
                     map_value.push((key, value));
                 }
+                let value = Value::Object(
+                    map_value
+                        .into_iter()
+                        .map(|(k, v)| (k.to_string(), v))
+                        .collect(),
+                );
+                // edit commands are evaluated after deserialization, with --edit only
+                if cmd::is_edit_command(&value) {
+                    return Ok(false);
+                }
                 Api::notify(ApiEvent::ProcessComplexCommand(
-                    &Value::Object(
-                        map_value
-                            .into_iter()
-                            .map(|(k, v)| (k.to_string(), v))
-                            .collect(),
-                    ),
+                    &value,
                     self.env_path,
                     self.cmd_path,
                     self.cmd_args,
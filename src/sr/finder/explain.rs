@@ -0,0 +1,175 @@
+//! Decision trace of the finder, used by `dosr --explain`
+//! Tasks hidden by `execinfo` are skipped at deserialization, so they never appear in the trace.
+use std::{cmp::Ordering, fmt::Display};
+
+use rar_common::database::score::{ActorMatchMin, Score};
+use serde::Serialize;
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Explanation {
+    pub roles: Vec<RoleTrace>,
+    /// Role and task finally selected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected: Option<(String, String)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoleTrace {
+    pub role: String,
+    pub user_min: ActorMatchMin,
+    pub matching: bool,
+    pub tasks: Vec<TaskTrace>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskTrace {
+    pub task: String,
+    pub verdict: Verdict,
+    pub score: Score,
+    /// Why the selected task beat this one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Verdict {
+    /// The task denies the env override requested with --preserve-env
+    EnvOverrideDenied,
+    /// The task does not grant every capability requested with --caps
+    CapsNotGranted,
+    /// The command matches a `del` entry of the task
+    CommandDenied,
+    /// No command matches better than the best candidate so far
+    CommandNotMatched,
+    /// The task matches, but its score is not better than the best candidate so far
+    NotBetter,
+    /// The task was the best candidate, until a better one was found
+    Outranked,
+    Selected,
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Verdict::EnvOverrideDenied => "env override denied",
+            Verdict::CapsNotGranted => "requested capabilities not granted",
+            Verdict::CommandDenied => "command explicitly denied",
+            Verdict::CommandNotMatched => "command not matched",
+            Verdict::NotBetter => "matched, not better",
+            Verdict::Outranked => "matched, outranked",
+            Verdict::Selected => "selected",
+        })
+    }
+}
+
+/// Explains `Score::better_fully`: the first component of `Score::cmp` that differs
+pub fn deciding_component(winner: &Score, other: &Score) -> &'static str {
+    if !other.fully_matching() {
+        return "the other task does not fully match";
+    }
+    let components = [
+        (
+            winner.cmd_min.order.cmp(&other.cmd_min.order),
+            "more specific command match (cmd_min)",
+        ),
+        (
+            winner.caps_min.cmp(&other.caps_min),
+            "fewer capabilities (caps_min)",
+        ),
+        (
+            winner.setuser_min.cmp(&other.setuser_min),
+            "less privileged user/group switch (setuser_min)",
+        ),
+        (
+            winner.security_min.cmp(&other.security_min),
+            "fewer security relaxations (security_min)",
+        ),
+        (
+            winner.user_min.cmp(&other.user_min),
+            "more specific actor match (user_min)",
+        ),
+    ];
+    components
+        .into_iter()
+        .find(|(ordering, _)| *ordering != Ordering::Equal)
+        .map(|(_, reason)| reason)
+        .unwrap_or("same score, the first declared task wins")
+}
+
+impl Explanation {
+    pub fn push_role(&mut self, role: &str, user_min: ActorMatchMin, matching: bool) {
+        self.roles.push(RoleTrace {
+            role: role.to_string(),
+            user_min,
+            matching,
+            tasks: Vec::new(),
+        });
+    }
+
+    pub fn push_task(&mut self, task: &str, verdict: Verdict, score: Score) {
+        if let Some(role) = self.roles.last_mut() {
+            role.tasks.push(TaskTrace {
+                task: task.to_string(),
+                verdict,
+                score,
+                reason: None,
+            });
+        }
+    }
+
+    /// Marks the final winner, previously selected tasks are outranked
+    pub fn conclude(&mut self, role: &str, task: Option<&str>, score: &Score) {
+        self.selected = task.map(|task| (role.to_string(), task.to_string()));
+        for role_trace in self.roles.iter_mut() {
+            for task_trace in role_trace.tasks.iter_mut() {
+                let selected = self
+                    .selected
+                    .as_ref()
+                    .is_some_and(|(r, t)| *r == role_trace.role && *t == task_trace.task);
+                if task_trace.verdict == Verdict::Selected && !selected {
+                    task_trace.verdict = Verdict::Outranked;
+                }
+                if matches!(task_trace.verdict, Verdict::NotBetter | Verdict::Outranked) {
+                    task_trace.reason = Some(deciding_component(score, &task_trace.score));
+                }
+            }
+        }
+    }
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for role in &self.roles {
+            writeln!(
+                f,
+                "Role: {} (actors {}: {:?})",
+                role.role,
+                if role.matching {
+                    "matched"
+                } else {
+                    "not matched"
+                },
+                role.user_min
+            )?;
+            for task in &role.tasks {
+                writeln!(f, "  Task: {}: {}", task.task, task.verdict)?;
+                writeln!(
+                    f,
+                    "    cmd_min: {:?}, caps_min: {:?}, setuser_min: {:?}, security_min: {:?}",
+                    task.score.cmd_min,
+                    task.score.caps_min,
+                    task.score.setuser_min,
+                    task.score.security_min
+                )?;
+                if let Some(reason) = task.reason {
+                    writeln!(f, "    Selected task wins with: {}", reason)?;
+                }
+            }
+        }
+        match &self.selected {
+            Some((role, task)) => write!(f, "Selected: role {}, task {}", role, task),
+            None => write!(f, "No task matches, the command is denied"),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Lists every task the user may run, used by `dosr --list`
+use std::fmt::Display;
+
+use rar_common::database::{
+    actor::DGroups,
+    options::{SAuthPolicy, SBounding, SPrivileged, SPty, SRecord, STimeout, SUMask},
+    structs::SetBehavior,
+};
+use serde::Serialize;
+
+use crate::error::SrResult;
+
+use super::{
+    de::{DCommand, DConfigFinder, DLinkedTask},
+    options::BorrowedOptStack,
+    BestExecSettings,
+};
+
+#[derive(Debug, Serialize)]
+pub struct ListedTask {
+    pub role: String,
+    pub task: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    pub commands: ListedCommands,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub setuid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub setgroups: Option<Vec<String>>,
+    pub capabilities: Vec<String>,
+    pub options: ListedOptions,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListedCommands {
+    pub all: bool,
+    pub add: Vec<String>,
+    pub del: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListedOptions {
+    pub path: Vec<String>,
+    pub authentication: SAuthPolicy,
+    pub timeout: STimeout,
+    pub bounding: SBounding,
+    pub root: SPrivileged,
+    pub umask: SUMask,
+    pub pty: SPty,
+    pub record: SRecord,
+}
+
+fn command_to_string(command: &DCommand<'_>) -> String {
+    match command {
+        DCommand::Simple(command) => command.to_string(),
+        DCommand::Complex(value) => value.to_string(),
+    }
+}
+
+impl ListedTask {
+    fn new(
+        task: &DLinkedTask<'_, '_, '_>,
+        opt_stack: &BorrowedOptStack<'_>,
+        env_path: &[&str],
+    ) -> Self {
+        let commands = task.commands.as_ref();
+        ListedTask {
+            role: task.role().role().role.to_string(),
+            task: task.id.to_string(),
+            purpose: task.purpose.as_ref().map(|p| p.to_string()),
+            commands: ListedCommands {
+                all: commands
+                    .and_then(|c| c.default_behavior)
+                    .is_some_and(|b| b == SetBehavior::All),
+                add: commands
+                    .map(|c| c.add.iter().map(command_to_string).collect())
+                    .unwrap_or_default(),
+                del: commands
+                    .map(|c| c.del.iter().map(command_to_string).collect())
+                    .unwrap_or_default(),
+            },
+            setuid: task.cred.setuid.as_ref().map(|u| u.to_string()),
+            setgroups: task.cred.setgroups.as_ref().map(|g| match g {
+                DGroups::Single(g) => vec![g.to_string()],
+                DGroups::Multiple(g) => g.iter().map(|g| g.to_string()).collect(),
+            }),
+            capabilities: task
+                .cred
+                .caps
+                .map(|caps| caps.iter().map(|c| c.to_string()).collect())
+                .unwrap_or_default(),
+            options: ListedOptions {
+                path: opt_stack.calc_path(env_path),
+                authentication: opt_stack.calc_authentication(),
+                timeout: opt_stack.calc_timeout(),
+                bounding: opt_stack.calc_bounding(),
+                root: opt_stack.calc_privileged(),
+                umask: opt_stack.calc_umask(),
+                pty: opt_stack.calc_pty(),
+                record: opt_stack.calc_record(),
+            },
+        }
+    }
+}
+
+impl Display for ListedTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Role: {}, Task: {}", self.role, self.task)?;
+        if let Some(purpose) = &self.purpose {
+            write!(f, " ({})", purpose)?;
+        }
+        writeln!(f)?;
+        if self.commands.all {
+            writeln!(f, "  Commands: all")?;
+        } else if self.commands.add.is_empty() {
+            writeln!(f, "  Commands: none")?;
+        } else {
+            writeln!(f, "  Commands: {}", self.commands.add.join(", "))?;
+        }
+        if !self.commands.del.is_empty() {
+            writeln!(f, "  Except: {}", self.commands.del.join(", "))?;
+        }
+        writeln!(
+            f,
+            "  Execute as user: {}, group(s): {}",
+            self.setuid.as_deref().unwrap_or("your current user"),
+            self.setgroups
+                .as_ref()
+                .map(|g| g.join(", "))
+                .unwrap_or("your current group(s)".to_string())
+        )?;
+        writeln!(
+            f,
+            "  With capabilities: {}",
+            if self.capabilities.is_empty() {
+                "None".to_string()
+            } else {
+                self.capabilities.join(" ")
+            }
+        )?;
+        let options = &self.options;
+        write!(
+            f,
+            "  Options: authentication={}, root={}, bounding={}, umask={:03o}, pty={}, record={}, path={}",
+            options.authentication.mode,
+            options.root,
+            options.bounding,
+            u16::from(options.umask),
+            options.pty,
+            options.record,
+            options.path.join(":")
+        )
+    }
+}
+
+/// Walks the policy and returns every task whose role actors match the user,
+/// tasks with a hidden `execinfo` are skipped.
+pub fn list_tasks(data: &DConfigFinder<'_>, env_path: &[&str]) -> SrResult<Vec<ListedTask>> {
+    let mut settings = BestExecSettings::default();
+    let mut tasks = Vec::new();
+    for role in data.roles() {
+        if !settings.actors_settings(&role)? {
+            continue;
+        }
+        for task in role.tasks() {
+            let opt_stack = BorrowedOptStack::from_task(&task);
+            if opt_stack.calc_info().is_hide() {
+                continue;
+            }
+            tasks.push(ListedTask::new(&task, &opt_stack, env_path));
+        }
+    }
+    Ok(tasks)
+}
@@ -10,13 +10,20 @@ use std::{
 use api::{register_plugins, Api, ApiEvent};
 use bon::Builder;
 use de::{ConfigFinderDeserializer, DConfigFinder, DLinkedCommand, DLinkedRole, DLinkedTask};
+pub use explain::Explanation;
+use explain::Verdict;
+pub use list::ListedTask;
 use log::debug;
-use options::BorrowedOptStack;
+use options::{BorrowedOptStack, DEnvOptions};
 use rar_common::{
     database::{
         actor::DGroups,
-        options::{SAuthentication, SBounding, SPrivileged, STimeout, SUMask},
+        options::{
+            SAuthPolicy, SBounding, SCapabilitiesMode, SCgroup, SCwd, SPrivileged, SPty, SRLimits,
+            SRecord, SSandbox, SSecureBits, STimeout, SUMask,
+        },
         score::{CmdMin, CmdOrder, Score},
+        structs::SCommands,
     },
     util::{all_paths_from_env, read_with_privileges},
     Cred, StorageMethod,
@@ -30,8 +37,10 @@ use crate::{
 };
 
 pub(crate) mod api;
-mod cmd;
+pub(crate) mod cmd;
 pub(crate) mod de;
+mod explain;
+mod list;
 mod options;
 
 #[derive(Debug, Default, Clone, Builder)]
@@ -49,16 +58,46 @@ pub struct BestExecSettings {
     pub env: HashMap<String, String>,
     #[builder(default)]
     pub env_path: Vec<String>,
+    /// Environment policy of the task, also applied to the PAM session variables
+    #[builder(default)]
+    pub env_policy: DEnvOptions<'static>,
     #[builder(default)]
     pub bounding: SBounding,
     #[builder(default)]
     pub timeout: STimeout,
     #[builder(default)]
-    pub auth: SAuthentication,
+    pub auth: SAuthPolicy,
     #[builder(default)]
     pub root: SPrivileged,
     #[builder(default)]
     pub umask: SUMask,
+    #[builder(default)]
+    pub pty: SPty,
+    #[builder(default)]
+    pub record: SRecord,
+    pub cwd: Option<SCwd>,
+    pub chroot: Option<String>,
+    #[builder(default)]
+    pub rlimits: SRLimits,
+    pub cgroup: Option<SCgroup>,
+    pub sandbox: Option<SSandbox>,
+    #[builder(default)]
+    pub noexec: bool,
+    #[builder(default)]
+    pub intercept: bool,
+    #[builder(default)]
+    pub securebits: SSecureBits,
+    #[builder(default)]
+    pub no_new_privs: bool,
+    #[builder(default)]
+    pub capabilities_mode: SCapabilitiesMode,
+    pub max_runtime: Option<chrono::Duration>,
+    /// Program asking the password for `dosr --askpass`
+    pub askpass: Option<PathBuf>,
+    /// Commands of the task, checked again for each exec of the command with `intercept`
+    pub commands: Option<SCommands>,
+    /// Decision trace, only collected for `dosr --explain`
+    pub explanation: Option<Explanation>,
 }
 
 pub fn find_best_exec_settings<'de: 'a, 'a, P>(
@@ -68,6 +107,55 @@ pub fn find_best_exec_settings<'de: 'a, 'a, P>(
     env_vars: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>,
     env_path: &[&str],
 ) -> SrResult<BestExecSettings>
+where
+    P: AsRef<Path>,
+{
+    let config = deserialize_config(cli, cred, path, env_path)?;
+    BestExecSettings::retrieve_settings(cli, cred, &config, env_vars, env_path)
+}
+
+/// Lists the tasks that `cred` may run, see `dosr --list`
+pub fn list_exec_settings<P>(
+    cli: &Cli,
+    cred: &Cred,
+    path: &P,
+    env_path: &[&str],
+) -> SrResult<Vec<ListedTask>>
+where
+    P: AsRef<Path>,
+{
+    let config = deserialize_config(cli, cred, path, env_path)?;
+    list::list_tasks(&config, env_path)
+}
+
+/// Traces how each role and task is evaluated for the command, see `dosr --explain`
+pub fn explain_exec_settings<P>(
+    cli: &Cli,
+    cred: &Cred,
+    path: &P,
+    env_path: &[&str],
+) -> SrResult<Explanation>
+where
+    P: AsRef<Path>,
+{
+    let config = deserialize_config(cli, cred, path, env_path)?;
+    let mut result = BestExecSettings {
+        explanation: Some(Explanation::default()),
+        ..Default::default()
+    };
+    let mut opt_stack = BorrowedOptStack::new(config.options.clone());
+    result.evaluate_roles(cli, &config, &mut opt_stack, env_path)?;
+    let mut explanation = result.explanation.take().unwrap_or_default();
+    explanation.conclude(&result.role, result.task.as_deref(), &result.score);
+    Ok(explanation)
+}
+
+fn deserialize_config<'a, P>(
+    cli: &'a Cli,
+    cred: &'a Cred,
+    path: &P,
+    env_path: &'a [&'a str],
+) -> SrResult<DConfigFinder<'a>>
 where
     P: AsRef<Path>,
 {
@@ -81,57 +169,48 @@ where
         cred,
         env_path,
     };
+    let file_path = settings_file
+        .storage
+        .settings
+        .unwrap_or_default()
+        .path
+        .ok_or(SrError::ConfigurationError)?;
+    let file = read_with_privileges(&file_path)?;
+    let reader = BufReader::new(file); // Use BufReader for efficient streaming
     match settings_file.storage.method {
         StorageMethod::CBOR => {
-            let file_path = settings_file
-                .storage
-                .settings
-                .unwrap_or_default()
-                .path
-                .ok_or(SrError::ConfigurationError)?;
-            let file = read_with_privileges(&file_path)?;
-            let reader = BufReader::new(file); // Use BufReader for efficient streaming
             let mut io_reader = cbor4ii::core::utils::IoReader::new(reader); // Use IoReader for streaming
-            Ok(BestExecSettings::retrieve_settings(
-                cli,
-                cred,
-                &config_finder_deserializer
-                    .deserialize(&mut cbor4ii::serde::Deserializer::new(&mut io_reader))
-                    .map_err(|e| {
-                        debug!("Error deserializing CBOR: {}", e);
-                        SrError::ConfigurationError
-                    })?,
-                env_vars,
-                env_path,
-            )?)
+            config_finder_deserializer
+                .deserialize(&mut cbor4ii::serde::Deserializer::new(&mut io_reader))
+                .map_err(|e| {
+                    debug!("Error deserializing CBOR: {}", e);
+                    SrError::ConfigurationError
+                })
         }
         StorageMethod::JSON => {
-            let file_path = settings_file
-                .storage
-                .settings
-                .unwrap_or_default()
-                .path
-                .ok_or(SrError::ConfigurationError)?;
-            let file = read_with_privileges(&file_path)?;
-            let reader = BufReader::new(file);
             let io_reader = serde_json::de::IoRead::new(reader);
-            Ok(BestExecSettings::retrieve_settings(
-                cli,
-                cred,
-                &config_finder_deserializer
-                    .deserialize(&mut serde_json::Deserializer::new(io_reader))
-                    .map_err(|e| {
-                        debug!("Error deserializing JSON: {}", e);
-                        SrError::ConfigurationError
-                    })?,
-                env_vars,
-                env_path,
-            )?)
+            config_finder_deserializer
+                .deserialize(&mut serde_json::Deserializer::new(io_reader))
+                .map_err(|e| {
+                    debug!("Error deserializing JSON: {}", e);
+                    SrError::ConfigurationError
+                })
         }
     }
 }
 
 impl BestExecSettings {
+    /// Adds the variables of the PAM session that the environment policy keeps,
+    /// the variables set by dosr and by the task are not overridden
+    pub fn merge_pam_env(&mut self, pam_env: impl IntoIterator<Item = (String, String)>) {
+        for (key, value) in pam_env {
+            if !self.env.contains_key(&key) && self.env_policy.is_kept(&key, &value) {
+                debug!("PAM session variable {} kept", key);
+                self.env.insert(key, value);
+            }
+        }
+    }
+
     fn retrieve_settings<'a>(
         cli: &'a Cli,
         cred: &'a Cred,
@@ -140,45 +219,76 @@ impl BestExecSettings {
         env_path: &[&str],
     ) -> SrResult<Self> {
         let mut result = Self::default();
-        let mut matching = false;
         let mut opt_stack = BorrowedOptStack::new(data.options.clone());
+        if !result.evaluate_roles(cli, data, &mut opt_stack, env_path)? {
+            return Err(SrError::PermissionDenied);
+        }
+        let env_policy =
+            opt_stack.calc_temp_env(opt_stack.calc_override_behavior(), &cli.opt_filter);
+        result.env = env_policy.calc_final_env(
+            env_vars,
+            opt_stack.calc_path(env_path),
+            cred,
+            &result.cred.setuid,
+            format!(
+                "{}{}",
+                cli.cmd_path.display(),
+                if cli.cmd_args.is_empty() {
+                    "".into()
+                } else {
+                    format!(" {}", cli.cmd_args.join(" "))
+                }
+            ),
+        )?;
+        result.env_policy = env_policy.into_owned();
+        result.auth = opt_stack.calc_authentication();
+        result.bounding = opt_stack.calc_bounding();
+        result.timeout = opt_stack.calc_timeout();
+        result.root = opt_stack.calc_privileged();
+        result.umask = opt_stack.calc_umask();
+        result.pty = opt_stack.calc_pty();
+        result.record = opt_stack.calc_record();
+        result.cwd = opt_stack.calc_cwd();
+        result.chroot = opt_stack.calc_chroot();
+        result.rlimits = opt_stack.calc_rlimits();
+        result.cgroup = opt_stack.calc_cgroup();
+        result.sandbox = opt_stack.calc_sandbox();
+        result.noexec = opt_stack.calc_noexec();
+        result.intercept = opt_stack.calc_intercept();
+        result.securebits = opt_stack.calc_securebits();
+        result.no_new_privs = opt_stack.calc_no_new_privs();
+        result.capabilities_mode = opt_stack.calc_capabilities_mode();
+        result.max_runtime = opt_stack.calc_max_runtime();
+        result.askpass = opt_stack.calc_askpass();
+        Ok(result)
+    }
+
+    fn evaluate_roles<'a>(
+        &mut self,
+        cli: &'a Cli,
+        data: &'a DConfigFinder<'a>,
+        opt_stack: &mut BorrowedOptStack<'a>,
+        env_path: &[&str],
+    ) -> SrResult<bool> {
+        let mut matching = false;
         for role in data.roles() {
-            matching |= result.role_settings(cli, &role, &mut opt_stack, env_path)?;
+            matching |= self.role_settings(cli, &role, opt_stack, env_path)?;
             Api::notify(ApiEvent::BestRoleSettingsFound(
                 cli,
                 &role,
-                &mut opt_stack,
+                opt_stack,
                 &env_path,
-                &mut result,
+                self,
                 &mut matching,
             ))?;
         }
-        if !matching {
-            return Err(SrError::PermissionDenied);
+        Ok(matching)
+    }
+
+    fn trace_task(&mut self, data: &DLinkedTask<'_, '_, '_>, verdict: Verdict, score: Score) {
+        if let Some(explanation) = self.explanation.as_mut() {
+            explanation.push_task(&data.id.to_string(), verdict, score);
         }
-        result.env = opt_stack
-            .calc_temp_env(opt_stack.calc_override_behavior(), &cli.opt_filter)
-            .calc_final_env(
-                env_vars,
-                opt_stack.calc_path(env_path),
-                cred,
-                &result.cred.setuid,
-                format!(
-                    "{}{}",
-                    cli.cmd_path.display(),
-                    if cli.cmd_args.is_empty() {
-                        "".into()
-                    } else {
-                        format!(" {}", cli.cmd_args.join(" "))
-                    }
-                ),
-            )?;
-        result.auth = opt_stack.calc_authentication();
-        result.bounding = opt_stack.calc_bounding();
-        result.timeout = opt_stack.calc_timeout();
-        result.root = opt_stack.calc_privileged();
-        result.umask = opt_stack.calc_umask();
-        Ok(result)
     }
 
     pub fn role_settings<'c, 'a>(
@@ -189,7 +299,11 @@ impl BestExecSettings {
         env_path: &[&str],
     ) -> SrResult<bool> {
         debug!("role_settings: {:?}", data.role().role);
-        if !self.actors_settings(data)? {
+        let actors_matching = self.actors_settings(data)?;
+        if let Some(explanation) = self.explanation.as_mut() {
+            explanation.push_role(&data.role().role, data.role().user_min, actors_matching);
+        }
+        if !actors_matching {
             return Ok(false);
         }
         let mut res = false;
@@ -224,9 +338,19 @@ impl BestExecSettings {
             .is_some_and(|f| f.env_behavior.is_some() && !temp_opt_stack.calc_override_behavior())
         {
             debug!("task_settings: deny task due to inherited from role or config env_override requirement");
+            self.trace_task(data, Verdict::EnvOverrideDenied, Score::default());
+            return Ok(false);
+        }
+        if cli
+            .opt_filter
+            .as_ref()
+            .is_some_and(|f| !f.grants_caps(data.cred.caps))
+        {
+            debug!("task_settings: deny task not granting the capabilities requested with --caps");
+            self.trace_task(data, Verdict::CapsNotGranted, Score::default());
             return Ok(false);
         }
-        if cli.info && temp_opt_stack.calc_info().is_hide() {
+        if (cli.info || cli.explain) && temp_opt_stack.calc_info().is_hide() {
             debug!("task_settings: deny task due to inherited from role or config info hide");
             return Ok(false);
         }
@@ -238,6 +362,7 @@ impl BestExecSettings {
                     cli,
                     &command,
                 )? {
+                    self.trace_task(data, Verdict::CommandDenied, Score::default());
                     return Ok(false);
                 }
             }
@@ -246,7 +371,10 @@ impl BestExecSettings {
                 let t_env_path = opt_stack.calc_path(env_path);
                 found = true;
                 debug!("{:?}", &cli.cmd_path);
-                if let Ok(path) = cli.cmd_path.canonicalize() {
+                if cli.edit {
+                    // edited files may not exist yet
+                    self.final_path = cli.cmd_path.clone();
+                } else if let Ok(path) = cli.cmd_path.canonicalize() {
                     self.final_path = path;
                 } else {
                     self.final_path = all_paths_from_env(
@@ -254,7 +382,7 @@ impl BestExecSettings {
                         &cli.cmd_path,
                     )
                     .first()
-                    .ok_or(SrError::ExecutionFailed)?
+                    .ok_or(SrError::CommandNotFound)?
                     .to_path_buf();
                 }
                 self.score.cmd_min = CmdMin::builder()
@@ -282,7 +410,17 @@ impl BestExecSettings {
         Api::notify(ApiEvent::BestTaskSettingsFound(
             cli, data, opt_stack, self, &mut score,
         ))?;
-        if found && score.better_fully(&self.score) {
+        let better = found && score.better_fully(&self.score);
+        self.trace_task(
+            data,
+            match (found, better) {
+                (false, _) => Verdict::CommandNotMatched,
+                (true, false) => Verdict::NotBetter,
+                (true, true) => Verdict::Selected,
+            },
+            score,
+        );
+        if better {
             debug!("found better task settings");
             self.role = data.role().role().role.to_string();
             self.task = Some(data.id.to_string());
@@ -297,7 +435,12 @@ impl BestExecSettings {
                 DGroups::Single(g) => vec![g.fetch_group()].into_iter().flatten().collect(),
                 DGroups::Multiple(g) => g.iter().filter_map(|g| g.fetch_group()).collect(),
             });
-            self.cred.caps = data.cred.caps;
+            // the score is kept on the task capabilities: the least privileged task is preferred
+            self.cred.caps = cli
+                .opt_filter
+                .as_ref()
+                .map_or(data.cred.caps, |f| f.narrow_caps(data.cred.caps));
+            self.commands = data.commands.as_ref().map(SCommands::from);
             opt_stack.set_role(data);
             opt_stack.set_task(data);
             debug!("resulting settings: {:?}", self);
@@ -314,6 +457,16 @@ impl BestExecSettings {
     ) -> SrResult<bool> {
         debug!("env_path: {:?}", env_path);
         Ok(match &**data {
+            // edit commands only match files given with --edit, and other commands never match them
+            de::DCommand::Simple(_) if cli.edit => false,
+            de::DCommand::Complex(value) if cmd::is_edit_command(value) => {
+                let cmd_min = if cli.edit {
+                    cmd::evaluate_edit_match(&cli.cmd_path, &cli.cmd_args, value)
+                } else {
+                    CmdMin::empty()
+                };
+                cmd_min.matching() && self.update_command_score(cli.cmd_path.clone(), cmd_min)
+            }
             de::DCommand::Simple(role_cmd) => {
                 let mut final_path = None;
                 let cmd_min = cmd::evaluate_command_match(
@@ -371,7 +524,7 @@ impl BestExecSettings {
 mod tests {
     use super::de::{DCommand, DCommandList, DRoleFinder, DTaskFinder, IdTask};
     use super::*;
-    use capctl::CapSet;
+    use capctl::{Cap, CapSet};
     use rar_common::database::options::{EnvBehavior, Level, SInfo};
     use rar_common::database::score::{ActorMatchMin, CmdMin, Score};
     use rar_common::database::structs::SetBehavior;
@@ -480,6 +633,133 @@ mod tests {
             .build()
     }
 
+    #[test]
+    fn test_list_tasks() {
+        let mut data = dummy_dconfigfinder();
+        let tasks = list::list_tasks(&data, &["/bin"]).unwrap();
+        // tasks with a hidden execinfo are not listed
+        assert_eq!(
+            tasks
+                .iter()
+                .map(|t| (t.role.as_str(), t.task.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("test", "1"), ("test2", "0")]
+        );
+        assert_eq!(tasks[0].commands.add.len(), 2);
+        assert!(tasks[0].capabilities.is_empty());
+        assert!(tasks[1].to_string().starts_with("Role: test2, Task: 0\n"));
+        data.roles[1].user_min = ActorMatchMin::NoMatch;
+        let tasks = list::list_tasks(&data, &["/bin"]).unwrap();
+        assert_eq!(tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_explain_settings() {
+        let cli = Cli::builder()
+            .cmd_path("/usr/bin/ls")
+            .cmd_args(["-l"])
+            .explain()
+            .build();
+        let data = dummy_dconfigfinder();
+        let mut result = BestExecSettings {
+            explanation: Some(Explanation::default()),
+            ..Default::default()
+        };
+        let mut opt_stack = BorrowedOptStack::new(data.options.clone());
+        assert!(result
+            .evaluate_roles(&cli, &data, &mut opt_stack, &["/bin"])
+            .unwrap());
+        let mut explanation = result.explanation.take().unwrap();
+        explanation.conclude(&result.role, result.task.as_deref(), &result.score);
+        // tasks with a hidden execinfo are not traced
+        let traced = explanation
+            .roles
+            .iter()
+            .flat_map(|r| {
+                r.tasks
+                    .iter()
+                    .map(|t| (r.role.as_str(), t.task.as_str(), t.verdict))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            traced,
+            vec![
+                ("test", "1", Verdict::Selected),
+                ("test2", "0", Verdict::NotBetter)
+            ]
+        );
+        assert_eq!(
+            explanation.roles[1].tasks[0].reason,
+            Some("same score, the first declared task wins")
+        );
+        assert_eq!(
+            explanation.selected,
+            Some(("test".to_string(), "1".to_string()))
+        );
+        assert!(explanation
+            .to_string()
+            .ends_with("Selected: role test, task 1"));
+    }
+
+    #[test]
+    fn test_caps_filter() {
+        let cli = Cli::builder()
+            .cmd_path("/usr/bin/ls")
+            .cmd_args(["-l"])
+            .opt_filter(
+                FilterMatcher::builder()
+                    .caps(CapSet::from_iter([Cap::NET_RAW, Cap::CHOWN]))
+                    .drop_caps(CapSet::from_iter([Cap::CHOWN]))
+                    .build(),
+            )
+            .build();
+        let data = dummy_dconfigfinder();
+        let mut result = BestExecSettings {
+            explanation: Some(Explanation::default()),
+            ..Default::default()
+        };
+        let mut opt_stack = BorrowedOptStack::new(data.options.clone());
+        assert!(result
+            .evaluate_roles(&cli, &data, &mut opt_stack, &["/bin"])
+            .unwrap());
+        // the tasks without capabilities would be preferred without --caps
+        assert_eq!(result.task.as_deref(), Some("0"));
+        assert_eq!(result.cred.caps, Some(CapSet::from_iter([Cap::NET_RAW])));
+        let explanation = result.explanation.unwrap();
+        assert_eq!(
+            explanation.roles[0].tasks[1].verdict,
+            Verdict::CapsNotGranted
+        );
+    }
+
+    #[test]
+    fn test_merge_pam_env() {
+        let mut result = BestExecSettings::builder()
+            .env(HashMap::from([(
+                "PATH".to_string(),
+                "/usr/bin".to_string(),
+            )]))
+            .env_policy(
+                DEnvOptions::builder(EnvBehavior::Delete)
+                    .keep(["XDG_RUNTIME_DIR", "PATH"])
+                    .unwrap()
+                    .check(["LANG"])
+                    .unwrap()
+                    .build(),
+            )
+            .build();
+        result.merge_pam_env([
+            ("XDG_RUNTIME_DIR".to_string(), "/run/user/0".to_string()),
+            ("PATH".to_string(), "/tmp".to_string()),
+            ("LANG".to_string(), "/tmp/evil".to_string()),
+            ("MAIL".to_string(), "/var/mail/root".to_string()),
+        ]);
+        assert_eq!(result.env["XDG_RUNTIME_DIR"], "/run/user/0");
+        assert_eq!(result.env["PATH"], "/usr/bin");
+        assert!(!result.env.contains_key("LANG"));
+        assert!(!result.env.contains_key("MAIL"));
+    }
+
     #[test]
     fn test_retrieve_settings_no_matching_role() {
         let cli = Cli::builder().cmd_path("/usr/bin/cat".to_string()).build();
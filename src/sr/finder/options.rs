@@ -1,20 +1,25 @@
 use std::collections::HashSet;
+use std::path::PathBuf;
 use std::{borrow::Cow, collections::HashMap};
 
 use bon::{bon, builder, Builder};
+use chrono::Duration;
 
 use libc::PATH_MAX;
 use nix::unistd::User;
 use rar_common::database::options::{
-    EnvBehavior, Level, PathBehavior, SAuthentication, SBounding, SInfo, SPathOptions, SPrivileged,
-    STimeout, SUMask,
+    deserialize_absolute_path, deserialize_authentication, serialize_authentication, EnvBehavior,
+    Level, PathBehavior, SAuthPolicy, SAuthentication, SBounding, SCapabilitiesMode, SCgroup, SCwd,
+    SInfo, SPathOptions, SPrivileged, SPty, SRLimits, SRecord, SSandbox, SSecureBits, STimeout,
+    SUMask,
 };
 use rar_common::database::score::SecurityMin;
-use rar_common::database::FilterMatcher;
+use rar_common::database::{deserialize_duration, serialize_duration, FilterMatcher};
 use rar_common::util::{
     AUTHENTICATION, BOUNDING, ENV_CHECK_LIST, ENV_DEFAULT_BEHAVIOR, ENV_DELETE_LIST, ENV_KEEP_LIST,
     ENV_OVERRIDE_BEHAVIOR, ENV_PATH_ADD_LIST_SLICE, ENV_PATH_BEHAVIOR, ENV_PATH_REMOVE_LIST_SLICE,
-    ENV_SET_LIST, INFO, PRIVILEGED, TIMEOUT_DURATION, TIMEOUT_MAX_USAGE, TIMEOUT_TYPE, UMASK,
+    ENV_SET_LIST, INFO, PRIVILEGED, PTY, RECORD, TIMEOUT_DURATION, TIMEOUT_MAX_USAGE, TIMEOUT_TYPE,
+    UMASK,
 };
 use std::hash::Hash;
 
@@ -86,14 +91,60 @@ pub struct Opt<'a> {
     pub root: Option<SPrivileged>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bounding: Option<SBounding>,
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub authentication: Option<SAuthentication>,
+    #[serde(
+        default,
+        serialize_with = "serialize_authentication",
+        deserialize_with = "deserialize_authentication",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub authentication: Option<SAuthPolicy>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub execinfo: Option<SInfo>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub timeout: Option<STimeout>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub umask: Option<SUMask>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pty: Option<SPty>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record: Option<SRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<SCwd>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_absolute_path",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub chroot: Option<Cow<'a, str>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rlimits: Option<SRLimits>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cgroup: Option<SCgroup>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SSandbox>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noexec: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intercept: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub securebits: Option<SSecureBits>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_new_privs: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities_mode: Option<SCapabilitiesMode>,
+    #[serde(
+        default,
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_runtime: Option<Duration>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_absolute_path",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub askpass: Option<Cow<'a, str>>,
     #[serde(default, flatten)]
     pub _extra_fields: Value,
 }
@@ -107,10 +158,24 @@ impl<'a> Opt<'a> {
         env: Option<DEnvOptions<'a>>,
         root: Option<SPrivileged>,
         bounding: Option<SBounding>,
-        authentication: Option<SAuthentication>,
+        #[builder(into)] authentication: Option<SAuthPolicy>,
         execinfo: Option<SInfo>,
         timeout: Option<STimeout>,
         umask: Option<SUMask>,
+        pty: Option<SPty>,
+        record: Option<SRecord>,
+        cwd: Option<SCwd>,
+        #[builder(into)] chroot: Option<Cow<'a, str>>,
+        rlimits: Option<SRLimits>,
+        cgroup: Option<SCgroup>,
+        sandbox: Option<SSandbox>,
+        noexec: Option<bool>,
+        intercept: Option<bool>,
+        securebits: Option<SSecureBits>,
+        no_new_privs: Option<bool>,
+        capabilities_mode: Option<SCapabilitiesMode>,
+        max_runtime: Option<Duration>,
+        #[builder(into)] askpass: Option<Cow<'a, str>>,
         #[builder(default)] _extra_fields: Value,
     ) -> Self {
         Self {
@@ -123,6 +188,20 @@ impl<'a> Opt<'a> {
             execinfo,
             timeout,
             umask,
+            pty,
+            record,
+            cwd,
+            chroot,
+            rlimits,
+            cgroup,
+            sandbox,
+            noexec,
+            intercept,
+            securebits,
+            no_new_privs,
+            capabilities_mode,
+            max_runtime,
+            askpass,
             _extra_fields,
         }
     }
@@ -137,40 +216,15 @@ impl DEnvOptions<'_> {
         target: &Option<User>,
         command: String,
     ) -> SrResult<HashMap<String, String>> {
-        let mut final_set = match self.default_behavior {
-            EnvBehavior::Inherit => {
-                error!("Internal Error with environment behavior");
-                Err(SrError::ConfigurationError)
-            }
-            EnvBehavior::Delete => Ok(env_vars
-                .into_iter()
-                .filter_map(|(key, value)| {
-                    let needle = key.into().into();
-                    let value: String = value.into();
-                    if env_matches(&self.keep, &needle)
-                        || (env_matches(&self.check, &needle) && check_env(&needle, &value))
-                    {
-                        Some((needle.to_string(), value))
-                    } else {
-                        None
-                    }
-                })
-                .collect::<HashMap<String, String>>()),
-            EnvBehavior::Keep => Ok(env_vars
-                .into_iter()
-                .filter_map(|(key, value)| {
-                    let needle = key.into().into();
-                    let value: String = value.into();
-                    if !env_matches(&self.delete, &needle)
-                        || (env_matches(&self.check, &needle) && check_env(&needle, &value))
-                    {
-                        Some((needle.to_string(), value))
-                    } else {
-                        None
-                    }
-                })
-                .collect::<HashMap<String, String>>()),
-        }?;
+        if self.default_behavior.is_inherit() {
+            error!("Internal Error with environment behavior");
+            return Err(SrError::ConfigurationError);
+        }
+        let mut final_set = env_vars
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .filter(|(key, value)| self.is_kept(key, value))
+            .collect::<HashMap<String, String>>();
         final_set.insert(
             "PATH".into(),
             env_path.into_iter().fold(String::new(), |acc, path| {
@@ -204,6 +258,37 @@ impl DEnvOptions<'_> {
         );
         Ok(final_set)
     }
+
+    /// Whether the policy passes a variable of the calling environment to the command
+    pub fn is_kept(&self, key: &str, value: &str) -> bool {
+        let needle = Cow::Borrowed(key);
+        let checked = env_matches(&self.check, &needle) && check_env(key, value);
+        match self.default_behavior {
+            EnvBehavior::Delete => env_matches(&self.keep, &needle) || checked,
+            EnvBehavior::Keep => !env_matches(&self.delete, &needle) || checked,
+            EnvBehavior::Inherit => false,
+        }
+    }
+
+    pub fn into_owned(self) -> DEnvOptions<'static> {
+        fn owned(set: HashSet<Cow<'_, str>>) -> HashSet<Cow<'static, str>> {
+            set.into_iter()
+                .map(|k| Cow::Owned(k.into_owned()))
+                .collect()
+        }
+        DEnvOptions {
+            default_behavior: self.default_behavior,
+            override_behavior: self.override_behavior,
+            set: self
+                .set
+                .into_iter()
+                .map(|(k, v)| (Cow::Owned(k.into_owned()), Cow::Owned(v.into_owned())))
+                .collect(),
+            keep: owned(self.keep),
+            check: owned(self.check),
+            delete: owned(self.delete),
+        }
+    }
 }
 
 impl From<Opt<'_>> for rar_common::database::options::Opt {
@@ -267,6 +352,20 @@ impl From<Opt<'_>> for rar_common::database::options::Opt {
             .maybe_bounding(val.bounding)
             .maybe_authentication(val.authentication)
             .maybe_timeout(val.timeout)
+            .maybe_pty(val.pty)
+            .maybe_record(val.record)
+            .maybe_cwd(val.cwd)
+            .maybe_chroot(val.chroot.map(|c| c.to_string()))
+            .maybe_rlimits(val.rlimits)
+            .maybe_cgroup(val.cgroup)
+            .maybe_sandbox(val.sandbox)
+            .maybe_noexec(val.noexec)
+            .maybe_intercept(val.intercept)
+            .maybe_securebits(val.securebits)
+            .maybe_no_new_privs(val.no_new_privs)
+            .maybe_capabilities_mode(val.capabilities_mode)
+            .maybe_max_runtime(val.max_runtime)
+            .maybe_askpass(val.askpass.map(|a| a.to_string()))
             .build()
     }
 }
@@ -531,7 +630,7 @@ impl<'a, 'c, 't> BorrowedOptStack<'a> {
                 .security_min(&mut security_min)
                 .bounding(&o.bounding)
                 .root(&o.root)
-                .authentication(&o.authentication)
+                .authentication(&o.authentication.as_ref().map(|a| a.mode))
                 .env_behavior(&o.env.as_ref().map(|e| e.default_behavior))
                 .override_env(&o.env.as_ref().and_then(|e| e.override_behavior))
                 .path_behavior(&o.path.as_ref().map(|p| p.default_behavior))
@@ -709,11 +808,10 @@ impl<'a, 'c, 't> BorrowedOptStack<'a> {
             .next()
             .unwrap_or(INFO)
     }
-    pub fn calc_authentication(&self) -> SAuthentication {
+    pub fn calc_authentication(&self) -> SAuthPolicy {
         self.get_opt_iter_rev()
-            .filter_map(|o| o.authentication)
-            .next()
-            .unwrap_or(AUTHENTICATION)
+            .find_map(|o| o.authentication.clone())
+            .unwrap_or_else(|| AUTHENTICATION.into())
     }
     pub fn calc_privileged(&self) -> SPrivileged {
         self.get_opt_iter_rev()
@@ -727,6 +825,86 @@ impl<'a, 'c, 't> BorrowedOptStack<'a> {
             .next()
             .unwrap_or(UMASK)
     }
+    pub fn calc_pty(&self) -> SPty {
+        self.get_opt_iter_rev()
+            .filter_map(|o| o.pty)
+            .next()
+            .unwrap_or(PTY)
+    }
+    pub fn calc_record(&self) -> SRecord {
+        self.get_opt_iter_rev()
+            .filter_map(|o| o.record)
+            .next()
+            .unwrap_or(RECORD)
+    }
+    pub fn calc_cwd(&self) -> Option<SCwd> {
+        self.get_opt_iter_rev().find_map(|o| o.cwd.clone())
+    }
+    pub fn calc_chroot(&self) -> Option<String> {
+        self.get_opt_iter_rev()
+            .find_map(|o| o.chroot.as_ref().map(|c| c.to_string()))
+    }
+    /// Limits are merged by resource, the most specific level wins
+    pub fn calc_rlimits(&self) -> SRLimits {
+        let mut rlimits = SRLimits::new();
+        for opt in self.get_opt_iter_rev() {
+            for (resource, limit) in opt.rlimits.iter().flatten() {
+                rlimits.entry(*resource).or_insert(*limit);
+            }
+        }
+        rlimits
+    }
+    /// Limits are merged by file like rlimits, None when no level sets the option
+    pub fn calc_cgroup(&self) -> Option<SCgroup> {
+        let mut cgroup: Option<SCgroup> = None;
+        for opt in self.get_opt_iter_rev() {
+            if let Some(limits) = &opt.cgroup {
+                let cgroup = cgroup.get_or_insert_with(Default::default);
+                for (file, value) in limits {
+                    cgroup.entry(*file).or_insert_with(|| value.clone());
+                }
+            }
+        }
+        cgroup
+    }
+    pub fn calc_sandbox(&self) -> Option<SSandbox> {
+        self.get_opt_iter_rev().find_map(|o| o.sandbox.clone())
+    }
+    pub fn calc_noexec(&self) -> bool {
+        self.get_opt_iter_rev()
+            .find_map(|o| o.noexec)
+            .unwrap_or_default()
+    }
+    pub fn calc_intercept(&self) -> bool {
+        self.get_opt_iter_rev()
+            .find_map(|o| o.intercept)
+            .unwrap_or_default()
+    }
+    pub fn calc_securebits(&self) -> SSecureBits {
+        self.get_opt_iter_rev()
+            .find_map(|o| o.securebits.clone())
+            .unwrap_or_default()
+    }
+    pub fn calc_no_new_privs(&self) -> bool {
+        self.get_opt_iter_rev()
+            .find_map(|o| o.no_new_privs)
+            .unwrap_or_default()
+    }
+    pub fn calc_capabilities_mode(&self) -> SCapabilitiesMode {
+        self.get_opt_iter_rev()
+            .find_map(|o| o.capabilities_mode)
+            .unwrap_or_default()
+    }
+    /// Only the global options may name the askpass program, the roles are not trusted with it
+    pub fn calc_askpass(&self) -> Option<PathBuf> {
+        self.config
+            .as_ref()
+            .and_then(|o| o.askpass.as_deref())
+            .map(PathBuf::from)
+    }
+    pub fn calc_max_runtime(&self) -> Option<Duration> {
+        self.get_opt_iter_rev().find_map(|o| o.max_runtime)
+    }
 }
 
 #[bon::builder]
@@ -815,6 +993,8 @@ fn calculate_combined_paths(
 
 #[cfg(test)]
 mod tests {
+    use rar_common::database::options::{SRLimit, SRLimitResource, SRLimitValue, SSecureBit};
+
     use super::*;
 
     #[test]
@@ -1000,6 +1180,9 @@ mod tests {
 
     #[test]
     fn test_borrowed_opt_stack() {
+        let limit = |value| {
+            SRLimit::new(SRLimitValue::Limited(value), SRLimitValue::Limited(value)).unwrap()
+        };
         let config = Some(
             Opt::builder(Level::Global)
                 .env(
@@ -1009,6 +1192,21 @@ mod tests {
                         .set([("VAR1", "VALUE1"), ("VAR2", "VALUE2")])
                         .build(),
                 )
+                .rlimits(SRLimits::from([
+                    (SRLimitResource::Nofile, limit(1024)),
+                    (SRLimitResource::Core, limit(0)),
+                ]))
+                .securebits(SSecureBits::from([SSecureBit::Noroot]))
+                .no_new_privs(true)
+                .capabilities_mode(SCapabilitiesMode::Inheritable)
+                .max_runtime(Duration::minutes(10))
+                .askpass("/usr/bin/ssh-askpass")
+                .authentication(
+                    SAuthPolicy::builder()
+                        .service("dosr-admin")
+                        .retries(2)
+                        .build(),
+                )
                 .build(),
         );
         let role = Some(
@@ -1029,6 +1227,12 @@ mod tests {
                         .unwrap()
                         .build(),
                 )
+                .pty(SPty::Never)
+                .cwd(SCwd::Any)
+                .sandbox(SSandbox::builder().pid(true).build())
+                .noexec(true)
+                .rlimits(SRLimits::from([(SRLimitResource::Nofile, limit(64))]))
+                .askpass("/tmp/askpass")
                 .build(),
         );
         let mut stack = BorrowedOptStack::new(config);
@@ -1038,6 +1242,39 @@ mod tests {
             stack.calc_path(&["/test"]),
             env!("RAR_PATH_ADD_LIST").split(':').collect::<Vec<&str>>()
         );
+        assert_eq!(stack.calc_pty(), SPty::Never);
+        assert_eq!(stack.calc_record(), SRecord::Disabled);
+        assert_eq!(stack.calc_cwd(), Some(SCwd::Any));
+        assert_eq!(stack.calc_chroot(), None);
+        assert!(stack.calc_sandbox().is_some_and(|s| s.pid && s.private_tmp));
+        assert!(stack.calc_noexec());
+        assert!(!stack.calc_intercept());
+        assert_eq!(
+            stack.calc_securebits(),
+            SSecureBits::from([SSecureBit::Noroot])
+        );
+        assert!(stack.calc_no_new_privs());
+        assert_eq!(
+            stack.calc_capabilities_mode(),
+            SCapabilitiesMode::Inheritable
+        );
+        assert_eq!(stack.calc_max_runtime(), Some(Duration::minutes(10)));
+        assert_eq!(
+            stack.calc_askpass(),
+            Some(PathBuf::from("/usr/bin/ssh-askpass"))
+        );
+        let auth = stack.calc_authentication();
+        assert_eq!(auth.mode, SAuthentication::Perform);
+        assert_eq!(auth.service.as_deref(), Some("dosr-admin"));
+        assert_eq!(auth.retries, Some(2));
+        assert!(!stack.calc_security_min().contains(SecurityMin::SkipAuth));
+        assert_eq!(
+            stack.calc_rlimits(),
+            SRLimits::from([
+                (SRLimitResource::Core, limit(0)),
+                (SRLimitResource::Nofile, limit(64)),
+            ])
+        );
         let env = stack.calc_temp_env(false, &None);
         assert_eq!(env.delete, HashSet::from(["DELETEME".into()]));
         assert_eq!(env.keep, HashSet::from(["KEEPME".into()]));
@@ -0,0 +1,343 @@
+//! Intercept mode, every exec of the command and its children is checked against the commands of the task.
+//! The command loads a seccomp filter notifying its `execve` and `execveat` calls, and sends the
+//! notification file descriptor to a dosr thread. This thread reads the path and the arguments in the
+//! memory of the caller, logs the exec, and allows or denies it. Like `noexec`, dosr executes the command
+//! itself (see `exec`), and the filter lets this exec through: the thread is started once the command is spawned.
+//! A multi-threaded program may change its memory after the check: intercept checks the sub-commands
+//! of the granted programs, it does not contain hostile programs.
+
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{IoSlice, IoSliceMut},
+    os::{
+        fd::{AsFd, AsRawFd, FromRawFd, OwnedFd, RawFd},
+        unix::{ffi::OsStringExt, fs::FileExt, net::UnixStream},
+    },
+    path::{Path, PathBuf},
+};
+
+use capctl::Cap;
+use libseccomp::{
+    error::SeccompError, notify_id_valid, ScmpAction, ScmpArch, ScmpArgCompare, ScmpCompareOp,
+    ScmpFilterContext, ScmpNotifReq, ScmpNotifResp, ScmpNotifRespFlags, ScmpSyscall,
+};
+use log::{debug, error, info, warn};
+use nix::{
+    cmsg_space,
+    errno::Errno,
+    poll::{poll, PollFd, PollFlags, PollTimeout},
+    sys::socket::{recvmsg, sendmsg, ControlMessage, ControlMessageOwned, MsgFlags},
+};
+use rar_common::{database::structs::SCommands, util::with_privileges};
+
+use crate::{
+    error::{SrError, SrResult},
+    exec::Exec,
+    finder::{api::register_plugins, cmd::is_command_allowed, BestExecSettings},
+};
+
+/// Upper bounds of the strings and arguments read in the caller memory, like ARG_MAX
+const MAX_STRING: usize = 128 * 1024;
+const MAX_ARGS: usize = 4096;
+const PAGE_SIZE: u64 = 4096;
+
+struct Supervisor {
+    commands: SCommands,
+    env_path: Vec<String>,
+    chroot: Option<String>,
+    execveat: ScmpSyscall,
+}
+
+/// The dosr side of the intercept socket, see `start`
+pub struct Intercept {
+    supervisor: Supervisor,
+    socket: UnixStream,
+}
+
+/// Prepares the supervisor, the returned socket is given to `install` in the command.
+/// The compact storage only keeps the score of the commands: intercept is refused without them.
+pub fn prepare(execcfg: &BestExecSettings) -> SrResult<(Intercept, UnixStream)> {
+    let commands = execcfg.commands.clone().ok_or_else(|| {
+        error!("intercept needs the commands of the task, they are not kept with the CBOR storage");
+        SrError::ConfigurationError
+    })?;
+    let (socket, command_socket) = UnixStream::pair()?;
+    let supervisor = Supervisor {
+        commands,
+        env_path: execcfg.env_path.clone(),
+        chroot: execcfg.chroot.clone(),
+        execveat: ScmpSyscall::from_name("execveat").map_err(std::io::Error::other)?,
+    };
+    Ok((Intercept { supervisor, socket }, command_socket))
+}
+
+impl Intercept {
+    /// Starts the dosr thread checking the execs, after the spawn: dosr must not fork while multi-threaded.
+    /// The thread keeps the capabilities of the caller, `CAP_SYS_PTRACE` is needed to read the caller memory.
+    pub fn start(self) -> SrResult<()> {
+        let Intercept { supervisor, socket } = self;
+        std::thread::Builder::new()
+            .name("intercept".to_string())
+            .spawn(move || {
+                // plugins are registered per thread, complex commands are matched by them
+                register_plugins();
+                if let Err(e) = supervisor.run(&socket) {
+                    error!("Intercept stopped: {}", e);
+                }
+            })?;
+        Ok(())
+    }
+}
+
+/// Notifies every exec but the one of the command, whose path argument is at `path`
+fn filter(path: u64) -> Result<ScmpFilterContext, SeccompError> {
+    let mut ctx = ScmpFilterContext::new(ScmpAction::Allow)?;
+    ctx.add_rule_conditional(
+        ScmpAction::Notify,
+        ScmpSyscall::from_name("execve")?,
+        &[ScmpArgCompare::new(0, ScmpCompareOp::NotEqual, path)],
+    )?;
+    ctx.add_rule(ScmpAction::Notify, ScmpSyscall::from_name("execveat")?)?;
+    Ok(ctx)
+}
+
+/// Loads the filter in the command before its exec, and sends the notification file descriptor to dosr
+pub fn install(socket: &UnixStream, exec: &Exec) -> std::io::Result<()> {
+    let listener = filter(exec.path_address())
+        .and_then(|ctx| {
+            ctx.load()?;
+            ctx.get_notify_fd()
+        })
+        .map_err(std::io::Error::other)?;
+    let listener = unsafe { OwnedFd::from_raw_fd(listener) };
+    sendmsg::<()>(
+        socket.as_raw_fd(),
+        &[IoSlice::new(&[0])],
+        &[ControlMessage::ScmRights(&[listener.as_raw_fd()])],
+        MsgFlags::empty(),
+        None,
+    )?;
+    Ok(())
+}
+
+/// None when the command closed the socket without starting
+fn receive_listener(socket: &UnixStream) -> std::io::Result<Option<OwnedFd>> {
+    let mut buf = [0u8; 1];
+    let mut iov = [IoSliceMut::new(&mut buf)];
+    let mut cmsg = cmsg_space!(RawFd);
+    let msg = recvmsg::<()>(
+        socket.as_raw_fd(),
+        &mut iov,
+        Some(&mut cmsg),
+        MsgFlags::MSG_CMSG_CLOEXEC,
+    )?;
+    for cmsg in msg.cmsgs()? {
+        if let ControlMessageOwned::ScmRights(fds) = cmsg {
+            return Ok(fds.first().map(|fd| unsafe { OwnedFd::from_raw_fd(*fd) }));
+        }
+    }
+    Ok(None)
+}
+
+/// Waits for a notification, false once every process of the command has exited
+fn wait_request(listener: &OwnedFd) -> std::io::Result<bool> {
+    loop {
+        let mut fds = [PollFd::new(listener.as_fd(), PollFlags::POLLIN)];
+        match poll(&mut fds, PollTimeout::NONE) {
+            Err(Errno::EINTR) => continue,
+            Err(e) => return Err(e.into()),
+            Ok(_) => {
+                let revents = fds[0].revents().unwrap_or(PollFlags::POLLHUP);
+                return Ok(revents.contains(PollFlags::POLLIN));
+            }
+        }
+    }
+}
+
+/// Reads a NUL terminated string, page by page as the next page may not be mapped
+fn read_string(mem: &File, mut addr: u64) -> std::io::Result<Vec<u8>> {
+    let mut result = Vec::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let len = (PAGE_SIZE - addr % PAGE_SIZE).min(buf.len() as u64) as usize;
+        let n = mem.read_at(&mut buf[..len], addr)?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        if let Some(end) = buf[..n].iter().position(|b| *b == 0) {
+            result.extend_from_slice(&buf[..end]);
+            return Ok(result);
+        }
+        result.extend_from_slice(&buf[..n]);
+        if result.len() > MAX_STRING {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+        addr += n as u64;
+    }
+}
+
+/// Reads a NULL terminated array of strings, a NULL array is empty
+fn read_argv(mem: &File, addr: u64) -> std::io::Result<Vec<String>> {
+    let mut argv = Vec::new();
+    if addr == 0 {
+        return Ok(argv);
+    }
+    loop {
+        let mut ptr = [0u8; 8];
+        mem.read_exact_at(&mut ptr, addr + 8 * argv.len() as u64)?;
+        let ptr = u64::from_ne_bytes(ptr);
+        if ptr == 0 {
+            return Ok(argv);
+        }
+        if argv.len() >= MAX_ARGS {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+        argv.push(String::from_utf8_lossy(&read_string(mem, ptr)?).into_owned());
+    }
+}
+
+/// Paths of /proc links are seen from the dosr root, the command sees them from its chroot
+fn in_root(path: PathBuf, chroot: Option<&str>) -> PathBuf {
+    match chroot.map(|root| path.strip_prefix(root)) {
+        Some(Ok(relative)) => Path::new("/").join(relative),
+        _ => path,
+    }
+}
+
+impl Supervisor {
+    fn run(&self, socket: &UnixStream) -> std::io::Result<()> {
+        let Some(listener) = receive_listener(socket)? else {
+            debug!("The command did not start, nothing to intercept");
+            return Ok(());
+        };
+        while wait_request(&listener)? {
+            let req = match ScmpNotifReq::receive(listener.as_raw_fd()) {
+                Ok(req) => req,
+                Err(e) => {
+                    // the caller may have been killed meanwhile
+                    debug!("Unable to receive the notification: {}", e);
+                    continue;
+                }
+            };
+            let allowed = self.check(listener.as_raw_fd(), &req);
+            let resp = if allowed {
+                ScmpNotifResp::new_continue(req.id, ScmpNotifRespFlags::empty())
+            } else {
+                ScmpNotifResp::new_error(req.id, -libc::EACCES, ScmpNotifRespFlags::empty())
+            };
+            if let Err(e) = resp.respond(listener.as_raw_fd()) {
+                debug!("Unable to respond to the notification: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    fn check(&self, listener: RawFd, req: &ScmpNotifReq) -> bool {
+        let exec = self.read_exec(req).and_then(|exec| {
+            // the caller may have been replaced while its memory was read
+            notify_id_valid(listener, req.id).map_err(std::io::Error::other)?;
+            Ok(exec)
+        });
+        match exec {
+            Ok((path, args)) => {
+                let env_path: Vec<&str> = self.env_path.iter().map(String::as_str).collect();
+                let allowed = is_command_allowed(&self.commands, &env_path, &path, &args);
+                let command = shell_words::join(
+                    std::iter::once(path.to_string_lossy().into_owned()).chain(args),
+                );
+                if allowed {
+                    info!("intercept: pid {} executes {}", req.pid, command);
+                } else {
+                    warn!("intercept: pid {} denied {}", req.pid, command);
+                }
+                allowed
+            }
+            Err(e) => {
+                warn!(
+                    "intercept: unable to read the exec of pid {}: {}",
+                    req.pid, e
+                );
+                false
+            }
+        }
+    }
+
+    /// Path and arguments, without argv[0], of the exec
+    fn read_exec(&self, req: &ScmpNotifReq) -> std::io::Result<(PathBuf, Vec<String>)> {
+        if req.data.arch != ScmpArch::native() {
+            return Err(std::io::Error::other("foreign architecture"));
+        }
+        let args = req.data.args;
+        let (dirfd, path, argv) = if req.data.syscall == self.execveat {
+            (args[0] as i32, args[1], args[2])
+        } else {
+            (libc::AT_FDCWD, args[0], args[1])
+        };
+        let mem = with_privileges(&[Cap::SYS_PTRACE], || {
+            File::open(format!("/proc/{}/mem", req.pid))
+        })?;
+        let path = PathBuf::from(OsString::from_vec(read_string(&mem, path)?));
+        let argv = read_argv(&mem, argv)?;
+        let path = if path.is_absolute() {
+            path
+        } else {
+            let base = if dirfd == libc::AT_FDCWD {
+                format!("/proc/{}/cwd", req.pid)
+            } else {
+                format!("/proc/{}/fd/{}", req.pid, dirfd)
+            };
+            let base = with_privileges(&[Cap::SYS_PTRACE], || std::fs::read_link(base))?;
+            in_root(base, self.chroot.as_deref()).join(path)
+        };
+        Ok((path, argv.into_iter().skip(1).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_memory() {
+        let mem = File::open("/proc/self/mem").unwrap();
+        let path = c"/usr/bin/ls";
+        let arg = c"-l";
+        let argv = [path.as_ptr(), arg.as_ptr(), std::ptr::null()];
+        assert_eq!(
+            read_string(&mem, path.as_ptr() as u64).unwrap(),
+            b"/usr/bin/ls"
+        );
+        assert_eq!(
+            read_argv(&mem, argv.as_ptr() as u64).unwrap(),
+            vec!["/usr/bin/ls", "-l"]
+        );
+        assert!(read_argv(&mem, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_prepare() {
+        let execcfg = BestExecSettings::builder().build();
+        assert!(matches!(
+            prepare(&execcfg),
+            Err(SrError::ConfigurationError)
+        ));
+        let execcfg = BestExecSettings::builder()
+            .commands(SCommands::default())
+            .build();
+        assert!(prepare(&execcfg).is_ok());
+        assert!(filter(0x1000).is_ok());
+    }
+
+    #[test]
+    fn test_in_root() {
+        assert_eq!(
+            in_root(PathBuf::from("/srv/jail/home"), Some("/srv/jail")),
+            PathBuf::from("/home")
+        );
+        assert_eq!(
+            in_root(PathBuf::from("/home"), None),
+            PathBuf::from("/home")
+        );
+    }
+}
@@ -1,28 +1,54 @@
+mod cgroup;
+mod edit;
 mod error;
+mod exec;
 mod finder;
+mod intercept;
+mod noexec;
 pub mod pam;
+mod record;
+mod relay;
+mod runtime;
+mod sandbox;
 #[cfg(feature = "timeout")]
 mod timeout;
 
 use bon::Builder;
-use capctl::{Cap, CapState};
+use capctl::{prctl::Secbits, Cap, CapSet, CapState};
 use const_format::formatcp;
 use finder::BestExecSettings;
-use nix::{sys::stat, unistd::isatty};
-use rar_common::util::{escape_parser_string, initialize_capabilities, with_privileges};
+use nix::{
+    sys::{
+        resource::{setrlimit, Resource, RLIM_INFINITY},
+        signal::{SigHandler, Signal},
+        stat,
+    },
+    unistd::{getgrouplist, isatty, Group, User},
+};
+use rar_common::util::{
+    escape_parser_string, initialize_capabilities, parse_capset_iter, with_privileges,
+};
 use rar_common::{
     database::{
         actor::{SGroupType, SGroups, SUserType},
-        options::EnvBehavior,
+        options::{EnvBehavior, SCwd, SPty, SRLimitResource, SRLimitValue},
         FilterMatcher,
     },
     Cred,
 };
 
-use log::{debug, error};
-use pam::PAM_PROMPT;
+use log::{debug, error, warn};
 use pty_process::blocking::{Command, Pty};
-use std::{io::stdout, path::PathBuf};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    io::{stdin, stdout},
+    os::unix::process::{CommandExt, ExitStatusExt},
+    path::{Path, PathBuf},
+    process::{ExitCode, ExitStatus, Termination},
+};
+use strum::{EnumIs, EnumString};
 
 use rar_common::util::{activates_no_new_privs, drop_effective, subsribe, BOLD, RST, UNDERLINE};
 
@@ -62,15 +88,64 @@ const USAGE: &str = formatcp!(
           Prompt option allows you to override the default password prompt and use a custom one
           [default: "Password: "]
 
+  {BOLD}-A, --askpass{RST}
+          Ask the password with the askpass program set in the global options, when there is no terminal
+
+  {BOLD}-n, --non-interactive{RST}
+          Never prompt: fail if a password is needed, unless the task skips authentication or a timestamp is still valid
+
   {BOLD}-u, --user <USER>{RST}
           Specify the user to execute the command as
 
   {BOLD}-g --group <GROUP>(,<GROUP>...){RST}
           Specify the group to execute the command as
 
+  {BOLD}--caps <CAP>(,<CAP>...){RST}
+          Only give these capabilities to the command, the selected task must grant all of them
+
+  {BOLD}--drop-caps <CAP>(,<CAP>...){RST}
+          Do not give these capabilities to the command
+
+  {BOLD}-s, --shell{RST}
+          Start the shell of the target user, or run the command with this shell (-c)
+
+  {BOLD}--login{RST}
+          Like --shell, as a login shell: the environment is reset and the command starts in the target home directory
+
+  {BOLD}-D, --chdir <DIR>{RST}
+          Run the command in this directory, if the task allows it (cwd option set to "*")
+
+  {BOLD}-T, --command-timeout <[[HH:]MM:]SS>{RST}
+          Terminate the command after this time, it cannot exceed the max-runtime of the task
+
+  {BOLD}-e, --edit <FILE>...{RST}
+          Edit files with your editor, the files must be allowed by an edit command of a task
+
   {BOLD}-i, --info{RST}
           Display rights of executor
 
+  {BOLD}--explain{RST}
+          Explain how each role and task is evaluated for the command, and why one is selected
+
+  {BOLD}--format <FORMAT>{RST}
+          Output format of --info, --list and --explain (text, json)
+          [default: text]
+
+  {BOLD}-l, --list{RST}
+          List every task you may run, root may list the tasks of another user with --user
+
+  {BOLD}--no-pty{RST}
+          Do not allocate a pseudo-terminal, pass file descriptors straight through (unless the task requires one)
+
+  {BOLD}--reraise{RST}
+          When the command is killed by a signal, terminate dosr with the same signal instead of exiting with 128+signal
+
+  {BOLD}--replay <ID>{RST}
+          Replay a recorded session (see the record option)
+
+  {BOLD}--dump{RST}
+          With --replay, print the whole session at once instead of replaying it in real time
+
   {BOLD}-v, --version{RST}
           Print dosr version
 
@@ -81,6 +156,14 @@ const USAGE: &str = formatcp!(
     RST = RST
 );
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumIs, EnumString)]
+#[strum(serialize_all = "lowercase")]
+enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
 #[derive(Debug, Builder)]
 struct Cli {
     /// Role option allows you to select a specific role to use.
@@ -94,6 +177,41 @@ struct Cli {
     /// Display rights of executor
     info: bool,
 
+    #[builder(default, with = || true)]
+    /// Start the shell of the target user
+    shell: bool,
+
+    #[builder(default, with = || true)]
+    /// Start the shell of the target user as a login shell
+    login: bool,
+
+    #[builder(into)]
+    /// Working directory requested by the user
+    chdir: Option<PathBuf>,
+
+    /// Runtime requested by the user, below the task maximum runtime
+    command_timeout: Option<chrono::Duration>,
+
+    #[builder(default, with = || true)]
+    /// Edit the files given as command
+    edit: bool,
+
+    #[builder(default, with = || true)]
+    /// Explain the decision of the finder
+    explain: bool,
+
+    #[builder(default)]
+    /// Output format of --info, --list and --explain
+    format: OutputFormat,
+
+    #[builder(default, with = || true)]
+    /// List the tasks the user may run
+    list: bool,
+
+    #[builder(into)]
+    /// User whose tasks are listed, root only
+    list_user: Option<SUserType>,
+
     #[builder(default, with = || true)]
     /// Display help
     help: bool,
@@ -110,14 +228,38 @@ struct Cli {
     /// Use stdin for password prompt
     stdin: bool,
 
+    #[builder(default, with = || true)]
+    /// Use the askpass program of the global options for password prompt
+    askpass: bool,
+
+    #[builder(default, with = || true)]
+    /// Fail instead of prompting for a password
+    non_interactive: bool,
+
     #[builder(default, with = || false)]
     /// Delete timestamp cookie after successful authentication
     del_ts: bool,
+
+    #[builder(default, with = || true)]
+    /// Do not allocate a pseudo-terminal for the command
+    no_pty: bool,
+
+    #[builder(default, with = || true)]
+    /// Re-raise the signal that killed the command on dosr
+    reraise: bool,
+
+    #[builder(into)]
+    /// Recorded session to replay
+    replay: Option<String>,
+
+    #[builder(default, with = || true)]
+    /// Print the replayed session without timing
+    dump: bool,
 }
 
 impl Default for Cli {
     fn default() -> Self {
-        Cli::builder().prompt(PAM_PROMPT).build()
+        Cli::builder().build()
     }
 }
 
@@ -142,6 +284,8 @@ where
     let mut user: Option<SUserType> = None;
     let mut group: Option<SGroups> = None;
     let mut env = None;
+    let mut caps = None;
+    let mut drop_caps = None;
 
     while let Some(arg) = iter.next() {
         // matches only first options
@@ -159,9 +303,21 @@ where
                     )
                 });
             }
+            "--caps" => {
+                caps = Some(parse_caps_arg(iter.next(), "--caps")?);
+            }
+            "--drop-caps" => {
+                drop_caps = Some(parse_caps_arg(iter.next(), "--drop-caps")?);
+            }
             "-S" | "--stdin" => {
                 args.stdin = true;
             }
+            "-A" | "--askpass" => {
+                args.askpass = true;
+            }
+            "-n" | "--non-interactive" => {
+                args.non_interactive = true;
+            }
             "-r" | "--role" => {
                 role = iter.next().map(|s| escape_parser_string(s));
             }
@@ -185,6 +341,65 @@ where
             "-i" | "--info" => {
                 args.info = true;
             }
+            "-s" | "--shell" => {
+                args.shell = true;
+            }
+            "--login" => {
+                args.shell = true;
+                args.login = true;
+            }
+            "-D" | "--chdir" => {
+                args.chdir = Some(
+                    iter.next()
+                        .ok_or_else(|| {
+                            error!("Missing directory for --chdir option");
+                            SrError::InvalidAgruments
+                        })?
+                        .as_ref()
+                        .into(),
+                );
+            }
+            "-T" | "--command-timeout" => {
+                args.command_timeout = Some(parse_duration_arg(iter.next())?);
+            }
+            "-e" | "--edit" => {
+                args.edit = true;
+            }
+            "--explain" => {
+                args.explain = true;
+            }
+            "--format" => {
+                args.format = iter
+                    .next()
+                    .and_then(|s| s.as_ref().parse().ok())
+                    .ok_or_else(|| {
+                        error!("Invalid --format value, expected text or json");
+                        SrError::InvalidAgruments
+                    })?;
+            }
+            "-l" | "--list" => {
+                args.list = true;
+            }
+            "--no-pty" => {
+                args.no_pty = true;
+            }
+            "--reraise" => {
+                args.reraise = true;
+            }
+            "--replay" => {
+                args.replay = Some(
+                    iter.next()
+                        .ok_or_else(|| {
+                            error!("Missing session id for --replay option");
+                            SrError::InvalidAgruments
+                        })?
+                        .as_ref()
+                        .to_string(),
+                );
+            }
+            "--dump" => {
+                args.dump = true;
+            }
             "-h" | "--help" => {
                 args.help = true;
             }
@@ -203,6 +418,10 @@ where
             }
         }
     }
+    if args.list {
+        // with --list, --user designates the listed user
+        args.list_user = user.take();
+    }
     args.opt_filter = Some(
         FilterMatcher::builder()
             .maybe_role(role)
@@ -218,6 +437,8 @@ where
                 error!("Error parsing group: {}", e);
                 SrError::InvalidAgruments
             })?
+            .maybe_caps(caps)
+            .maybe_drop_caps(drop_caps)
             .build(),
     );
     for arg in iter {
@@ -226,11 +447,48 @@ where
     Ok(args)
 }
 
+fn parse_caps_arg<S: AsRef<str>>(arg: Option<S>, option: &str) -> SrResult<CapSet> {
+    let arg = arg.ok_or_else(|| {
+        error!("Missing capabilities for {} option", option);
+        SrError::InvalidAgruments
+    })?;
+    parse_capset_iter(arg.as_ref().split(',')).map_err(|_| {
+        error!("Invalid capabilities for {}: {}", option, arg.as_ref());
+        SrError::InvalidAgruments
+    })
+}
+
+/// Parses `[[hh:]mm:]ss`
+fn parse_duration_arg<S: AsRef<str>>(arg: Option<S>) -> SrResult<chrono::Duration> {
+    let arg = arg.ok_or_else(|| {
+        error!("Missing time for --command-timeout option");
+        SrError::InvalidAgruments
+    })?;
+    let parts = arg
+        .as_ref()
+        .split(':')
+        .map(|part| part.parse::<u32>().ok())
+        .collect::<Option<Vec<_>>>()
+        .filter(|parts| parts.len() <= 3)
+        .ok_or_else(|| {
+            error!("Invalid time for --command-timeout: {}", arg.as_ref());
+            SrError::InvalidAgruments
+        })?;
+    let seconds = parts
+        .into_iter()
+        .fold(0, |acc, part| acc * 60 + i64::from(part));
+    if seconds == 0 {
+        error!("--command-timeout must be greater than zero");
+        return Err(SrError::InvalidAgruments);
+    }
+    Ok(chrono::Duration::seconds(seconds))
+}
+
 #[cfg(not(tarpaulin_include))]
-fn main() {
+fn main() -> ExitCode {
     if let Err(e) = subsribe("sr") {
         eprintln!("sr: Failed to initialize logging: {}", e);
-        std::process::exit(1);
+        return SrError::SystemError.report();
     }
     if let Err(e) = main_inner() {
         eprintln!("sr: {}", e);
@@ -257,8 +515,9 @@ fn main() {
                 std::env::args().skip(1).collect::<Vec<_>>().join(" ")
             );
         }
-        std::process::exit(1);
+        return e.report();
     }
+    ExitCode::SUCCESS
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -276,13 +535,29 @@ fn main_inner() -> SrResult<()> {
         println!("{}", USAGE);
         return Ok(());
     }
-    let args = getopt(args)?;
+    let mut args = getopt(args)?;
 
     if args.help {
         println!("{}", USAGE);
         return Ok(());
     }
+    if let Some(id) = &args.replay {
+        return record::replay(id, args.dump);
+    }
     let user = make_cred();
+    if args.shell {
+        let shell = args
+            .opt_filter
+            .as_ref()
+            .and_then(|f| f.user)
+            .and_then(|uid| User::from_uid(uid.into()).ok().flatten())
+            .map(|u| u.shell)
+            .unwrap_or_else(|| user.user.shell.clone());
+        set_shell_command(&mut args, shell);
+    }
+    if args.edit {
+        set_edit_files(&mut args)?;
+    }
     if args.del_ts {
         #[cfg(not(feature = "timeout"))]
         {
@@ -300,21 +575,49 @@ fn main_inner() -> SrResult<()> {
             }
         }
     }
-    let execcfg = find_best_exec_settings(
-        &args,
-        &user,
-        &ROOTASROLE.to_string(),
-        env::vars(),
-        env::var("PATH")
-            .unwrap_or_default()
-            .split(':')
-            .collect::<Vec<_>>()
-            .as_slice(),
-    )?;
+    if args.list {
+        return list(&args, &user);
+    }
+    if args.explain {
+        return explain(&args, &user);
+    }
+    let find = |args: &Cli| {
+        find_best_exec_settings(
+            args,
+            &user,
+            &ROOTASROLE.to_string(),
+            env::vars(),
+            env::var("PATH")
+                .unwrap_or_default()
+                .split(':')
+                .collect::<Vec<_>>()
+                .as_slice(),
+        )
+    };
+    let mut execcfg = find(&args)?;
+    if args.shell {
+        // the matching task may switch to a user with another shell
+        if let Some(shell) = target_shell(&execcfg).filter(|s| *s != args.cmd_path) {
+            debug!("Target user shell is {}, searching again", shell.display());
+            args.cmd_path = shell;
+            execcfg = find(&args)?;
+            if target_shell(&execcfg).is_none_or(|s| s != args.cmd_path) {
+                error!("No task allows the shell of the target user");
+                return Err(SrError::PermissionDenied);
+            }
+        }
+    }
 
     debug!("Best exec settings: {:?}", execcfg);
 
-    check_auth(&execcfg.auth, &execcfg.timeout, &user, &args)?;
+    let authenticated = check_auth(
+        &execcfg.auth,
+        &execcfg.timeout,
+        &user,
+        execcfg.cred.setuid.as_ref(),
+        execcfg.askpass.as_deref(),
+        &args,
+    )?;
 
     if !execcfg.score.fully_matching() {
         println!("You are not allowed to execute this command, this incident will be reported.");
@@ -322,12 +625,18 @@ fn main_inner() -> SrResult<()> {
             "User {} tried to execute command : {:?} {:?} without the permission.",
             &user.user.name, args.cmd_path, args.cmd_args
         );
-
-        std::process::exit(1);
+        return Err(SrError::PermissionDenied);
     }
 
     if args.info {
         use capctl::CapSet;
+        if args.format.is_json() {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&info_json(&execcfg, &args))?
+            );
+            std::process::exit(0);
+        }
         println!(
             "Role: {}",
             if execcfg.role.is_empty() {
@@ -380,61 +689,604 @@ fn main_inner() -> SrResult<()> {
                     .to_string()
             }
         );
+        if execcfg.noexec {
+            println!("The command cannot execute other programs (noexec)");
+        } else if execcfg.intercept {
+            println!(
+                "Every program executed by the command is checked against the task (intercept)"
+            );
+        }
+        if !execcfg.securebits.is_empty() {
+            println!(
+                "With securebits: {}",
+                execcfg
+                    .securebits
+                    .iter()
+                    .map(|bit| bit.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        if let Some(max) = execcfg.max_runtime {
+            println!("Maximum runtime: {}s", max.num_seconds());
+        }
         println!("Command: {:?} {:?}", execcfg.final_path, args.cmd_args);
         std::process::exit(0);
     }
 
-    // disable root
-    if execcfg.root.is_user() {
-        activates_no_new_privs().expect("Failed to activate no new privs");
-    }
-
     debug!("setuid : {:?}", execcfg.cred.setuid);
 
+    if let Some(reason) = execcfg.capabilities_mode.unsupported() {
+        error!("{}", reason);
+        return Err(SrError::ConfigurationError);
+    }
+
     umask(execcfg.umask.into());
 
+    let workdir = working_dir(&execcfg, &args, &user)?;
+    let max_runtime = max_runtime(&execcfg, &args)?;
+
+    if args.edit {
+        if execcfg.chroot.is_some() {
+            error!("--edit is not supported for tasks with a chroot");
+            return Err(SrError::PermissionDenied);
+        }
+        let files = std::iter::once(args.cmd_path.clone())
+            .chain(args.cmd_args.iter().map(PathBuf::from))
+            .collect::<Vec<_>>();
+        return edit::edit(&execcfg, &user, &files);
+    }
+
+    // opened with the privileges of dosr by the transaction that authenticated the user, if any,
+    // the command inherits the limits set by the modules
+    let target = execcfg
+        .cred
+        .setuid
+        .as_ref()
+        .map_or(&user.user.name, |u| &u.name);
+    let mut pam_session = match authenticated {
+        Some(session) => session,
+        None => pam::Session::start(pam::service(&execcfg.auth), target, &user.user.name)?,
+    };
+    pam_session.open(target)?;
+    execcfg.merge_pam_env(pam_session.env());
+
+    let recorder = if execcfg.record.is_disabled() {
+        None
+    } else {
+        Some(record::Recorder::create(
+            execcfg.record,
+            &user,
+            &execcfg.final_path,
+            &args.cmd_args,
+            relay::window_size(&stdout()).unwrap_or((24, 80)),
+        )?)
+    };
+
+    let cgroup = cgroup::Cgroup::create(&execcfg)?;
+    let cgroup_procs = cgroup.as_ref().map(|c| c.procs()).transpose()?;
+
     setuid_setgid(&execcfg)?;
 
     set_capabilities(&execcfg)?;
 
-    let pty = Pty::new().expect("Failed to create pty");
+    let (intercept, intercept_socket) = intercepted(&execcfg)
+        .then(|| intercept::prepare(&execcfg))
+        .transpose()?
+        .unzip();
+
+    let pty = if use_pty(&execcfg, &args) {
+        Some(Pty::new().expect("Failed to create pty"))
+    } else {
+        debug!("Running without pseudo-terminal");
+        None
+    };
 
     debug!(
         "Command: {:?} {:?}",
         execcfg.final_path,
         args.cmd_args.join(" ")
     );
+    let reraise = args.reraise;
     let cargs = args.cmd_args.clone();
     let cfinal_path = execcfg.final_path.clone();
-    let cfinal_env = execcfg.env.clone();
-    let command = unsafe {
-        Command::new(&execcfg.final_path)
-            .pre_exec(move || {
-                use crate::finder::api::{Api, ApiEvent};
-                Api::notify(ApiEvent::PreExec(&args, &execcfg)).map_err(|e| {
-                    error!("Failed to notify pre-exec event: {}", e);
-                    std::io::Error::new(std::io::ErrorKind::Other, "Failed to notify pre-exec")
-                })?;
-                Ok(())
-            })
-            .args(cargs.iter())
-            .env_clear()
-            .envs(cfinal_env)
-            .stdin(std::process::Stdio::inherit())
-            .stdout(std::process::Stdio::inherit())
-            .stderr(std::process::Stdio::inherit())
+    let cfinal_env = if args.login {
+        login_env(&execcfg.env)
+    } else {
+        execcfg.env.clone()
+    };
+    // a login shell has a '-' prefixed argv[0]
+    let arg0 = args.login.then(|| login_arg0(&args.cmd_path));
+    // the seccomp filters of noexec and intercept let only this exec through
+    let exec = (execcfg.noexec || intercept_socket.is_some())
+        .then(|| exec::Exec::new(&cfinal_path, arg0.as_deref(), &cargs, &cfinal_env))
+        .transpose()?;
+    // the working directory is changed after the chroot, so it is resolved inside
+    let pre_exec = move || {
+        use crate::finder::api::{Api, ApiEvent};
+        if let Some(procs) = &cgroup_procs {
+            cgroup::join(procs)?;
+        }
+        prepare_command(&execcfg)?;
+        if let Some(dir) = &workdir {
+            std::env::set_current_dir(dir)?;
+        }
+        if let Some(exec) = &exec {
+            match &intercept_socket {
+                Some(socket) => intercept::install(socket, exec)?,
+                None => noexec::install(exec)?,
+            }
+        }
+        // last, the seccomp plugin may deny the syscalls used above
+        Api::notify(ApiEvent::PreExec(&args, &execcfg)).map_err(|e| {
+            error!("Failed to notify pre-exec event: {}", e);
+            std::io::Error::new(std::io::ErrorKind::Other, "Failed to notify pre-exec")
+        })?;
+        if let Some(exec) = &exec {
+            return Err(exec.exec());
+        }
+        Ok(())
+    };
+    // stdin is attached to the pty only when it is a terminal, pipes are passed through
+    let forward_stdin = isatty(stdin()).unwrap_or(false);
+    // before the spawn, a signal received meanwhile is forwarded once the command runs
+    let handlers = relay::SignalHandlers::install(pty.is_some()).map_err(|e| {
+        error!("Failed to install signal handlers: {}", e);
+        SrError::SystemError
+    })?;
+    let command = if let Some(pty) = &pty {
+        relay::copy_window_size(pty);
+        let mut command = Command::new(&cfinal_path);
+        unsafe {
+            command.pre_exec(pre_exec);
+        }
+        command.args(cargs.iter()).env_clear().envs(cfinal_env);
+        if let Some(arg0) = &arg0 {
+            command.arg0(arg0);
+        }
+        if !forward_stdin {
+            command.stdin(std::process::Stdio::inherit());
+        }
+        command
             .spawn(&pty.pts().expect("Failed to get pts"))
+            .map_err(|e| match e {
+                pty_process::Error::Io(e) => e,
+                e => std::io::Error::other(e),
+            })
+    } else {
+        let mut command = std::process::Command::new(&cfinal_path);
+        unsafe {
+            command.pre_exec(pre_exec);
+        }
+        command.args(cargs.iter()).env_clear().envs(cfinal_env);
+        if let Some(arg0) = &arg0 {
+            command.arg0(arg0);
+        }
+        command.spawn()
     };
     let mut command = match command {
         Ok(command) => command,
         Err(e) => {
             error!("{}", e);
             eprintln!("sr: {} : {}", cfinal_path.display(), e);
-            std::process::exit(1);
+            return Err(if e.kind() == std::io::ErrorKind::NotFound {
+                SrError::CommandNotFound
+            } else {
+                SrError::ExecutionFailed
+            });
         }
     };
+    // the thread is started with the capabilities of setup_caps
+    if let Some(Err(e)) = intercept.map(intercept::Intercept::start) {
+        // the execs of the command would never be answered
+        error!("Unable to intercept the execs of the command: {}", e);
+        let _ = command.kill();
+        let _ = command.wait();
+        return Err(SrError::ExecutionFailed);
+    }
+    if let Some(limit) = max_runtime {
+        if let Err(e) = runtime::supervise(command.id(), limit, cfinal_path.display().to_string()) {
+            // the command must not outlive its maximum runtime
+            error!("Unable to limit the runtime of the command: {}", e);
+            let _ = command.kill();
+            let _ = command.wait();
+            return Err(SrError::ExecutionFailed);
+        }
+    }
+    let child = nix::unistd::Pid::from_raw(command.id() as i32);
+    if let Some(pty) = &pty {
+        if let Err(e) = relay::relay(pty, child, handlers, forward_stdin, recorder) {
+            error!("Terminal relay failed: {}", e);
+        }
+    } else if let Err(e) = relay::forward_signals(handlers, child) {
+        error!("Failed to forward signals: {}", e);
+    }
     let status = command.wait().expect("Failed to wait for command");
-    std::process::exit(status.code().unwrap_or(1));
+    drop(cgroup);
+    drop(pam_session);
+    if let Some(signal) = status.signal() {
+        warn!(
+            "Command {} of user '{}' was terminated by signal {}{}",
+            cfinal_path.display(),
+            user.user.name,
+            Signal::try_from(signal)
+                .map(|s| s.as_str())
+                .unwrap_or("unknown"),
+            if status.core_dumped() {
+                " (core dumped)"
+            } else {
+                ""
+            }
+        );
+        if reraise {
+            reraise_signal(signal);
+        }
+    }
+    std::process::exit(exit_code(&status));
+}
+
+/// With --edit, the command line is the list of edited files, made absolute
+fn set_edit_files(args: &mut Cli) -> SrResult<()> {
+    if args.shell || args.cmd_path.as_os_str().is_empty() {
+        error!("--edit requires files, and cannot be used with --shell");
+        return Err(SrError::InvalidAgruments);
+    }
+    args.cmd_path = edit::absolute(&args.cmd_path)?;
+    args.cmd_args = args
+        .cmd_args
+        .iter()
+        .map(|file| edit::absolute(Path::new(file)).map(|f| f.display().to_string()))
+        .collect::<SrResult<_>>()?;
+    Ok(())
+}
+
+/// Directory where the command starts: `--chdir` when the task allows any directory,
+/// else the `cwd` option, else the home directory for a login shell.
+/// In a chroot, the command starts at its root by default.
+fn working_dir(execcfg: &BestExecSettings, args: &Cli, user: &Cred) -> SrResult<Option<PathBuf>> {
+    let home = || {
+        execcfg
+            .cred
+            .setuid
+            .as_ref()
+            .map(|u| u.dir.clone())
+            .unwrap_or_else(|| user.user.dir.clone())
+    };
+    let expand = |dir: &str| match dir.strip_prefix('~') {
+        Some(rest) => home().join(rest.trim_start_matches('/')),
+        None => PathBuf::from(dir),
+    };
+    let dir = match (&args.chdir, &execcfg.cwd) {
+        (Some(dir), Some(SCwd::Any)) => {
+            let dir = expand(&dir.to_string_lossy());
+            if !dir.is_absolute() {
+                error!("--chdir requires an absolute path");
+                return Err(SrError::InvalidAgruments);
+            }
+            Some(dir)
+        }
+        (Some(_), _) => {
+            error!("The task does not allow to choose the working directory");
+            return Err(SrError::PermissionDenied);
+        }
+        (None, Some(SCwd::Path(dir))) => Some(expand(dir)),
+        (None, _) if args.login => Some(home()),
+        (None, _) => None,
+    };
+    Ok(dir.or_else(|| execcfg.chroot.as_ref().map(|_| PathBuf::from("/"))))
+}
+
+/// The `max-runtime` of the task, or the `--command-timeout` of the user when it is shorter
+fn max_runtime(execcfg: &BestExecSettings, args: &Cli) -> SrResult<Option<std::time::Duration>> {
+    let limit = match (execcfg.max_runtime, args.command_timeout) {
+        (Some(max), Some(requested)) if requested > max => {
+            error!(
+                "--command-timeout cannot exceed the maximum runtime of the task ({}s)",
+                max.num_seconds()
+            );
+            return Err(SrError::PermissionDenied);
+        }
+        (max, requested) => requested.or(max),
+    };
+    Ok(limit.and_then(|limit| limit.to_std().ok()))
+}
+
+/// noexec denies every exec, there is nothing to intercept
+fn intercepted(execcfg: &BestExecSettings) -> bool {
+    execcfg.intercept && !execcfg.noexec
+}
+
+/// Capabilities needed by `prepare_command`, and by dosr to remove the cgroup of the command,
+/// kept in the permitted set by `set_capabilities`
+fn setup_caps(execcfg: &BestExecSettings) -> CapSet {
+    let mut caps = CapSet::empty();
+    if execcfg.cgroup.is_some() {
+        caps.add(Cap::DAC_OVERRIDE);
+    }
+    if execcfg.chroot.is_some() {
+        caps.add(Cap::SYS_CHROOT);
+    }
+    if !execcfg.rlimits.is_empty() {
+        // raising a hard limit requires CAP_SYS_RESOURCE
+        caps.add(Cap::SYS_RESOURCE);
+    }
+    if intercepted(execcfg) {
+        // the exec calls are read in the memory of the command
+        caps.add(Cap::SYS_PTRACE);
+    }
+    if let Some(sandbox) = &execcfg.sandbox {
+        caps.add(Cap::SYS_ADMIN);
+        if sandbox.net {
+            caps.add(Cap::NET_ADMIN);
+        }
+    }
+    caps
+}
+
+fn rlimit_resource(resource: SRLimitResource) -> Resource {
+    match resource {
+        SRLimitResource::As => Resource::RLIMIT_AS,
+        SRLimitResource::Core => Resource::RLIMIT_CORE,
+        SRLimitResource::Cpu => Resource::RLIMIT_CPU,
+        SRLimitResource::Data => Resource::RLIMIT_DATA,
+        SRLimitResource::Fsize => Resource::RLIMIT_FSIZE,
+        SRLimitResource::Locks => Resource::RLIMIT_LOCKS,
+        SRLimitResource::Memlock => Resource::RLIMIT_MEMLOCK,
+        SRLimitResource::Msgqueue => Resource::RLIMIT_MSGQUEUE,
+        SRLimitResource::Nice => Resource::RLIMIT_NICE,
+        SRLimitResource::Nofile => Resource::RLIMIT_NOFILE,
+        SRLimitResource::Nproc => Resource::RLIMIT_NPROC,
+        SRLimitResource::Rss => Resource::RLIMIT_RSS,
+        SRLimitResource::Rtprio => Resource::RLIMIT_RTPRIO,
+        SRLimitResource::Rttime => Resource::RLIMIT_RTTIME,
+        SRLimitResource::Sigpending => Resource::RLIMIT_SIGPENDING,
+        SRLimitResource::Stack => Resource::RLIMIT_STACK,
+    }
+}
+
+fn rlimit_value(value: SRLimitValue) -> u64 {
+    match value {
+        SRLimitValue::Limited(value) => value,
+        SRLimitValue::Unlimited => RLIM_INFINITY,
+    }
+}
+
+/// Applies the resource limits, the sandbox and the root directory in the command process,
+/// the capabilities of `setup_caps` and of the PAM session are then dropped unless the task has them
+fn prepare_command(execcfg: &BestExecSettings) -> std::io::Result<()> {
+    let setup = setup_caps(execcfg);
+    let mut state = CapState::get_current()?;
+    state.effective = setup;
+    state.set_current()?;
+    for (resource, limit) in &execcfg.rlimits {
+        debug!("setrlimit {} {} {}", resource, limit.soft, limit.hard);
+        setrlimit(
+            rlimit_resource(*resource),
+            rlimit_value(limit.soft),
+            rlimit_value(limit.hard),
+        )?;
+    }
+    if let Some(sandbox) = &execcfg.sandbox {
+        sandbox::enter(sandbox)?;
+    }
+    if let Some(root) = &execcfg.chroot {
+        nix::unistd::chroot(Path::new(root))?;
+    }
+    state.effective.clear();
+    state.permitted &=
+        !((setup | CapSet::from_iter(pam::SESSION_CAPS)) & !execcfg.cred.caps.unwrap_or_default());
+    state.set_current()?;
+    Ok(())
+}
+
+/// Variables kept by a login shell, like login(1) does
+const LOGIN_ENV: [&str; 6] = ["HOME", "SHELL", "USER", "LOGNAME", "PATH", "TERM"];
+
+/// With --shell, the command is replaced by the shell, and the command line is passed to `-c`
+fn set_shell_command(args: &mut Cli, shell: PathBuf) {
+    if !args.cmd_path.as_os_str().is_empty() {
+        let command = std::iter::once(args.cmd_path.display().to_string())
+            .chain(args.cmd_args.drain(..))
+            .map(|arg| shell_quote(&arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+        args.cmd_args = vec!["-c".to_string(), command];
+    }
+    args.cmd_path = shell;
+}
+
+/// Quotes an argument for a POSIX shell, if needed
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c))
+    {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Shell of the target user, as set in the final environment
+fn target_shell(execcfg: &BestExecSettings) -> Option<PathBuf> {
+    execcfg.env.get("SHELL").map(PathBuf::from)
+}
+
+fn login_env(env: &HashMap<String, String>) -> HashMap<String, String> {
+    env.iter()
+        .filter(|(key, _)| LOGIN_ENV.contains(&key.as_str()) || key.starts_with("RAR_"))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+fn login_arg0(shell: &Path) -> String {
+    format!(
+        "-{}",
+        shell
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default()
+    )
+}
+
+/// Shell compatible exit code: the command exit code, or 128 + signal number when it was killed
+fn exit_code(status: &ExitStatus) -> i32 {
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(1)
+}
+
+/// Terminates dosr with the signal that killed the command, returns only if the signal is not fatal
+fn reraise_signal(signal: i32) {
+    if let Ok(signal) = Signal::try_from(signal) {
+        debug!("Re-raising {} on dosr", signal);
+        unsafe {
+            let _ = nix::sys::signal::signal(signal, SigHandler::SigDfl);
+        }
+        let _ = nix::sys::signal::raise(signal);
+    }
+}
+
+/// Whether the command is spawned on a new pseudo-terminal, according to the
+/// `pty` option and the `--no-pty` flag. The policy always wins over the flag.
+/// Session recording always requires a pseudo-terminal.
+fn use_pty(execcfg: &BestExecSettings, args: &Cli) -> bool {
+    if !execcfg.record.is_disabled() {
+        if args.no_pty {
+            warn!("--no-pty is ignored, this session is recorded");
+        }
+        return true;
+    }
+    match execcfg.pty {
+        SPty::Always => {
+            if args.no_pty {
+                warn!("--no-pty is ignored, this task requires a pseudo-terminal");
+            }
+            true
+        }
+        SPty::Never => false,
+        SPty::Auto => {
+            !args.no_pty && isatty(stdin()).unwrap_or(false) && isatty(stdout()).unwrap_or(false)
+        }
+    }
+}
+
+/// Machine readable execution settings, printed by `--info --format json`
+fn info_json(execcfg: &BestExecSettings, args: &Cli) -> serde_json::Value {
+    let mut env_keys: Vec<&String> = execcfg.env.keys().collect();
+    env_keys.sort();
+    json!({
+        "role": (!execcfg.role.is_empty()).then_some(&execcfg.role),
+        "task": execcfg.task,
+        "final-path": execcfg.final_path,
+        "args": args.cmd_args,
+        "setuid": execcfg.cred.setuid.as_ref().map(|u| json!({
+            "name": u.name,
+            "uid": u.uid.as_raw(),
+        })),
+        "setgroups": execcfg.cred.setgroups.as_ref().map(|groups| groups
+            .iter()
+            .map(|g| json!({
+                "name": g.name,
+                "gid": g.gid.as_raw(),
+            }))
+            .collect::<Vec<_>>()),
+        "capabilities": execcfg
+            .cred
+            .caps
+            .map(|caps| caps.iter().map(|c| c.to_string()).collect::<Vec<_>>()),
+        "env": env_keys,
+        "bounding": execcfg.bounding,
+        "root": execcfg.root,
+        "umask": execcfg.umask,
+        "authentication": execcfg.auth,
+        "timeout": execcfg.timeout,
+        "cwd": execcfg.cwd,
+        "chroot": execcfg.chroot,
+        "rlimits": execcfg.rlimits,
+        "cgroup": execcfg.cgroup,
+        "sandbox": execcfg.sandbox,
+        "noexec": execcfg.noexec,
+        "intercept": execcfg.intercept,
+        "securebits": execcfg.securebits,
+        "capabilities-mode": execcfg.capabilities_mode,
+        "max-runtime": execcfg.max_runtime.map(|max| max.num_seconds()),
+        "no-new-privs": execcfg.no_new_privs || execcfg.root.is_user(),
+        "score": execcfg.score,
+    })
+}
+
+fn list(args: &Cli, user: &Cred) -> SrResult<()> {
+    use finder::list_exec_settings;
+    let listed;
+    let cred = match &args.list_user {
+        Some(list_user) => {
+            if !user.user.uid.is_root() {
+                error!("Only root may list the tasks of another user");
+                return Err(SrError::PermissionDenied);
+            }
+            let target = list_user.fetch_user().ok_or_else(|| {
+                error!("Unknown user {}", list_user);
+                SrError::InvalidAgruments
+            })?;
+            listed = cred_of(target)?;
+            &listed
+        }
+        None => user,
+    };
+    let env_path = std::env::var("PATH").unwrap_or_default();
+    let tasks = list_exec_settings(
+        args,
+        cred,
+        &ROOTASROLE.to_string(),
+        env_path.split(':').collect::<Vec<_>>().as_slice(),
+    )?;
+    if args.format.is_json() {
+        println!("{}", serde_json::to_string_pretty(&tasks)?);
+        return Ok(());
+    }
+    if tasks.is_empty() {
+        println!("{} may not run any command.", cred.user.name);
+    }
+    for task in tasks {
+        println!("{}", task);
+    }
+    Ok(())
+}
+
+fn explain(args: &Cli, user: &Cred) -> SrResult<()> {
+    use finder::explain_exec_settings;
+    let env_path = std::env::var("PATH").unwrap_or_default();
+    let explanation = explain_exec_settings(
+        args,
+        user,
+        &ROOTASROLE.to_string(),
+        env_path.split(':').collect::<Vec<_>>().as_slice(),
+    )?;
+    if args.format.is_json() {
+        println!("{}", serde_json::to_string_pretty(&explanation)?);
+    } else {
+        println!("{}", explanation);
+    }
+    Ok(())
+}
+
+/// Credentials of another user, with its groups from the group database
+fn cred_of(user: User) -> SrResult<Cred> {
+    let name = CString::new(user.name.as_str()).map_err(|_| SrError::InvalidAgruments)?;
+    let groups = getgrouplist(&name, user.gid)
+        .map_err(|_| SrError::SystemError)?
+        .into_iter()
+        .filter_map(|gid| Group::from_gid(gid).ok().flatten())
+        .collect();
+    Ok(Cred {
+        user,
+        groups,
+        tty: None,
+        ppid: nix::unistd::getppid(),
+    })
 }
 
 fn make_cred() -> Cred {
@@ -460,6 +1312,10 @@ fn set_capabilities(execcfg: &BestExecSettings) -> SrResult<()> {
     }
     initialize_capabilities(&[Cap::SETPCAP])
         .inspect_err(|_| error!("{}", cap_effective_error("setpcap")))?;
+    // only needed until the command is prepared, see prepare_command,
+    // but dosr closes the PAM session once the command exits
+    let kept = setup_caps(execcfg)
+        | (CapSet::from_iter(pam::SESSION_CAPS) & CapState::get_current()?.permitted);
     let mut capstate = CapState::empty();
     if execcfg.bounding.is_strict() {
         for cap in (!caps).iter() {
@@ -467,16 +1323,57 @@ fn set_capabilities(execcfg: &BestExecSettings) -> SrResult<()> {
         }
     }
     capstate.effective.clear();
-    capstate.permitted = caps;
+    capstate.permitted = caps | kept;
     capstate.inheritable = caps;
+    // the bits of the uid switch were set by setuid_setgid
+    let securebits = securebits(execcfg) & !SETUID_SECUREBITS;
+    if !securebits.is_empty() {
+        // kept until the securebits are set
+        capstate.effective.add(Cap::SETPCAP);
+        capstate.permitted.add(Cap::SETPCAP);
+    }
     debug!("caps : {:?}", caps);
     capstate.set_current().expect("Failed to set current cap");
-    for cap in caps.iter() {
-        capctl::ambient::raise(cap).expect("Failed to set ambiant cap");
+    if !execcfg.capabilities_mode.is_inheritable() {
+        for cap in caps.iter() {
+            capctl::ambient::raise(cap).expect("Failed to set ambiant cap");
+        }
+    }
+    if !securebits.is_empty() {
+        set_securebits(securebits)?;
+        capstate.effective.clear();
+        capstate.permitted = caps | kept;
+        capstate.set_current().expect("Failed to set current cap");
+    }
+    // disable root
+    if execcfg.no_new_privs || execcfg.root.is_user() {
+        activates_no_new_privs().inspect_err(|e| error!("Unable to set no_new_privs: {}", e))?;
     }
     Ok(())
 }
 
+/// Securebits governing the capabilities on uid switches, set before the one of dosr
+const SETUID_SECUREBITS: Secbits = Secbits::NO_SETUID_FIXUP.union(Secbits::NO_SETUID_FIXUP_LOCKED);
+
+fn securebits(execcfg: &BestExecSettings) -> Secbits {
+    execcfg
+        .securebits
+        .iter()
+        .fold(Secbits::empty(), |acc, bit| acc | (*bit).into())
+}
+
+/// Set after the ambient raise, that `no-cap-ambient-raise` would deny,
+/// except the `SETUID_SECUREBITS`, set before the uid switch.
+/// The bits already set, e.g. locked by a parent, are kept.
+fn set_securebits(securebits: Secbits) -> SrResult<()> {
+    debug!("securebits : {:?}", securebits);
+    let current = capctl::prctl::get_securebits()?;
+    capctl::prctl::set_securebits(current | securebits).map_err(|e| {
+        error!("Unable to set the securebits {:?}: {}", securebits, e);
+        SrError::InsufficientPrivileges
+    })
+}
+
 fn setuid_setgid(execcfg: &BestExecSettings) -> SrResult<()> {
     let gid = execcfg
         .cred
@@ -484,6 +1381,11 @@ fn setuid_setgid(execcfg: &BestExecSettings) -> SrResult<()> {
         .as_ref()
         .and_then(|g| g.first().cloned())
         .map(|g| g.gid.as_raw());
+    let securebits = securebits(execcfg) & SETUID_SECUREBITS;
+    if !securebits.is_empty() {
+        with_privileges(&[Cap::SETPCAP], || Ok(set_securebits(securebits)))
+            .inspect_err(|_| error!("{}", cap_effective_error("setpcap")))??;
+    }
     with_privileges(&[Cap::SETUID, Cap::SETGID], || {
         capctl::cap_set_ids(
             execcfg.cred.setuid.as_ref().map(|u| u.uid.as_raw()),
@@ -511,7 +1413,7 @@ mod tests {
     use capctl::{Cap, CapSet};
     use libc::getgid;
     use nix::unistd::{getgroups, getuid, Group, Pid, User};
-    use rar_common::database::options::SBounding;
+    use rar_common::database::options::{SBounding, SRLimit, SRecord, SSandbox};
 
     use super::*;
 
@@ -576,6 +1478,300 @@ mod tests {
         assert_eq!(args.cmd_args, vec!["-l".to_string()]);
     }
 
+    #[test]
+    fn test_getopt_caps() {
+        let args = getopt(vec![
+            "sr",
+            "--caps",
+            "CAP_NET_BIND_SERVICE,cap_net_raw",
+            "--drop-caps",
+            "CAP_NET_RAW",
+            "ls",
+        ])
+        .unwrap();
+        let opt_filter = args.opt_filter.unwrap();
+        assert_eq!(
+            opt_filter.caps,
+            Some(CapSet::from_iter([Cap::NET_BIND_SERVICE, Cap::NET_RAW]))
+        );
+        assert_eq!(
+            opt_filter.drop_caps,
+            Some(CapSet::from_iter([Cap::NET_RAW]))
+        );
+        assert_eq!(args.cmd_path, PathBuf::from("ls"));
+        assert!(getopt(vec!["sr", "--caps", "CAP_NOPE", "ls"]).is_err());
+        assert!(getopt(vec!["sr", "--drop-caps"]).is_err());
+    }
+
+    #[test]
+    fn test_getopt_command_timeout() {
+        let args = getopt(vec!["sr", "-T", "1:30", "sleep", "100"]).unwrap();
+        assert_eq!(args.command_timeout, Some(chrono::Duration::seconds(90)));
+        let args = getopt(vec!["sr", "--command-timeout", "2:00:05", "ls"]).unwrap();
+        assert_eq!(args.command_timeout, Some(chrono::Duration::seconds(7205)));
+        assert!(getopt(vec!["sr", "-T", "0", "ls"]).is_err());
+        assert!(getopt(vec!["sr", "-T", "1:2:3:4", "ls"]).is_err());
+        assert!(getopt(vec!["sr", "-T", "ten", "ls"]).is_err());
+        assert!(getopt(vec!["sr", "-T"]).is_err());
+    }
+
+    #[test]
+    fn test_max_runtime() {
+        let mut execcfg = BestExecSettings::default();
+        let mut args = Cli::default();
+        assert_eq!(max_runtime(&execcfg, &args).unwrap(), None);
+        args.command_timeout = Some(chrono::Duration::seconds(30));
+        assert_eq!(
+            max_runtime(&execcfg, &args).unwrap(),
+            Some(std::time::Duration::from_secs(30))
+        );
+        execcfg.max_runtime = Some(chrono::Duration::minutes(1));
+        assert_eq!(
+            max_runtime(&execcfg, &args).unwrap(),
+            Some(std::time::Duration::from_secs(30))
+        );
+        args.command_timeout = None;
+        assert_eq!(
+            max_runtime(&execcfg, &args).unwrap(),
+            Some(std::time::Duration::from_secs(60))
+        );
+        args.command_timeout = Some(chrono::Duration::minutes(2));
+        assert!(max_runtime(&execcfg, &args).is_err());
+    }
+
+    #[test]
+    fn test_getopt_askpass() {
+        let args = getopt(vec!["sr", "-A", "ls"]).unwrap();
+        assert!(args.askpass);
+        assert_eq!(args.cmd_path, PathBuf::from("ls"));
+        let args = getopt(vec!["sr", "--askpass", "ls"]).unwrap();
+        assert!(args.askpass);
+        assert!(!getopt(vec!["sr", "ls"]).unwrap().askpass);
+    }
+
+    #[test]
+    fn test_getopt_non_interactive() {
+        let args = getopt(vec!["sr", "-n", "ls"]).unwrap();
+        assert!(args.non_interactive);
+        assert_eq!(args.cmd_path, PathBuf::from("ls"));
+        let args = getopt(vec!["sr", "--non-interactive", "ls", "-n"]).unwrap();
+        assert!(args.non_interactive);
+        assert_eq!(args.cmd_args, vec!["-n".to_string()]);
+    }
+
+    #[test]
+    fn test_getopt_no_pty() {
+        let args = getopt(vec!["sr", "--no-pty", "tar", "c", "."]).unwrap();
+        assert!(args.no_pty);
+        assert_eq!(args.cmd_path, PathBuf::from("tar"));
+        assert_eq!(args.cmd_args, vec!["c".to_string(), ".".to_string()]);
+    }
+
+    #[test]
+    fn test_use_pty() {
+        let mut execcfg = BestExecSettings::default();
+        let no_pty = Cli::builder().no_pty().build();
+        execcfg.pty = SPty::Always;
+        assert!(use_pty(&execcfg, &no_pty));
+        execcfg.pty = SPty::Never;
+        assert!(!use_pty(&execcfg, &Cli::default()));
+        execcfg.pty = SPty::Auto;
+        assert!(!use_pty(&execcfg, &no_pty));
+        execcfg.pty = SPty::Never;
+        execcfg.record = SRecord::Asciicast;
+        assert!(use_pty(&execcfg, &no_pty));
+    }
+
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(exit_code(&ExitStatus::from_raw(0)), 0);
+        assert_eq!(exit_code(&ExitStatus::from_raw(3 << 8)), 3);
+        // killed by SIGKILL
+        assert_eq!(exit_code(&ExitStatus::from_raw(9)), 137);
+        // SIGSEGV with core dump flag
+        let status = ExitStatus::from_raw(0x80 | 11);
+        assert!(status.core_dumped());
+        assert_eq!(exit_code(&status), 139);
+        assert!(getopt(vec!["sr", "--reraise", "ls"]).unwrap().reraise);
+    }
+
+    #[test]
+    fn test_edit_files() {
+        let mut args = getopt(vec!["sr", "-e", "/etc/hosts", "motd"]).unwrap();
+        assert!(args.edit);
+        set_edit_files(&mut args).unwrap();
+        assert_eq!(args.cmd_path, PathBuf::from("/etc/hosts"));
+        assert!(Path::new(&args.cmd_args[0]).is_absolute());
+        let mut args = getopt(vec!["sr", "--edit"]).unwrap();
+        assert!(set_edit_files(&mut args).is_err());
+        let mut args = getopt(vec!["sr", "-e", "/etc/../etc/shadow"]).unwrap();
+        assert!(set_edit_files(&mut args).is_err());
+    }
+
+    #[test]
+    fn test_shell_command() {
+        let mut args = getopt(vec!["sr", "-s"]).unwrap();
+        assert!(args.shell && !args.login);
+        set_shell_command(&mut args, "/bin/bash".into());
+        assert_eq!(args.cmd_path, PathBuf::from("/bin/bash"));
+        assert!(args.cmd_args.is_empty());
+        let mut args = getopt(vec!["sr", "--login", "echo", "it's", "$HOME"]).unwrap();
+        assert!(args.shell && args.login);
+        set_shell_command(&mut args, "/bin/sh".into());
+        assert_eq!(args.cmd_path, PathBuf::from("/bin/sh"));
+        assert_eq!(args.cmd_args, vec!["-c", r#"echo 'it'\''s' '$HOME'"#]);
+        assert_eq!(login_arg0(Path::new("/bin/bash")), "-bash");
+        let env = login_env(&HashMap::from([
+            ("HOME".to_string(), "/root".to_string()),
+            ("RAR_USER".to_string(), "user".to_string()),
+            ("LD_PRELOAD".to_string(), "evil.so".to_string()),
+        ]));
+        assert_eq!(env.len(), 2);
+        assert!(!env.contains_key("LD_PRELOAD"));
+    }
+
+    #[test]
+    fn test_getopt_format() {
+        let args = getopt(vec!["sr", "-i", "--format", "json", "ls"]).unwrap();
+        assert!(args.format.is_json());
+        assert!(getopt(vec!["sr", "--format", "yaml", "ls"]).is_err());
+        assert!(getopt(vec!["sr", "ls"]).unwrap().format.is_text());
+    }
+
+    #[test]
+    fn test_info_json() {
+        let args = Cli::builder()
+            .cmd_path("/usr/bin/ls")
+            .cmd_args(["-l"])
+            .build();
+        let execcfg = BestExecSettings::builder()
+            .role("r1".to_string())
+            .task("t1".to_string())
+            .final_path("/usr/bin/ls".into())
+            .env([("PATH".to_string(), "/bin".to_string())].into())
+            .cred(
+                CredOwnedData::builder()
+                    .setuid(User::from_uid(0.into()).unwrap().unwrap())
+                    .caps(CapSet::empty())
+                    .build(),
+            )
+            .build();
+        let info = info_json(&execcfg, &args);
+        assert_eq!(info["role"], "r1");
+        assert_eq!(info["task"], "t1");
+        assert_eq!(info["final-path"], "/usr/bin/ls");
+        assert_eq!(info["args"], json!(["-l"]));
+        assert_eq!(info["setuid"]["uid"], 0);
+        assert!(info["setgroups"].is_null());
+        assert_eq!(info["capabilities"], json!([]));
+        assert_eq!(info["env"], json!(["PATH"]));
+        assert!(info["umask"].is_string());
+        assert!(info["score"]["cmd-min"].is_object());
+        assert!(info["score"]["setuser-min"].is_object());
+        assert!(info["score"]["cmd_min"].is_null());
+        assert!(info["cwd"].is_null());
+        assert_eq!(info["noexec"], false);
+        assert!(info["no-new-privs"].is_boolean());
+        assert!(info["max-runtime"].is_null());
+    }
+
+    #[test]
+    fn test_setup_caps() {
+        assert!(setup_caps(&BestExecSettings::default()).is_empty());
+        let execcfg = BestExecSettings::builder()
+            .chroot("/jail".to_string())
+            .rlimits(
+                [(
+                    SRLimitResource::Nofile,
+                    SRLimit::new(SRLimitValue::Limited(64), SRLimitValue::Unlimited).unwrap(),
+                )]
+                .into(),
+            )
+            .build();
+        let caps = setup_caps(&execcfg);
+        assert!(caps.has(Cap::SYS_CHROOT) && caps.has(Cap::SYS_RESOURCE));
+        assert!(!caps.has(Cap::SYS_ADMIN));
+        let execcfg = BestExecSettings::builder()
+            .sandbox(SSandbox::builder().net(true).build())
+            .build();
+        let caps = setup_caps(&execcfg);
+        assert!(caps.has(Cap::SYS_ADMIN) && caps.has(Cap::NET_ADMIN));
+        let execcfg = BestExecSettings::builder().intercept(true).build();
+        assert!(setup_caps(&execcfg).has(Cap::SYS_PTRACE));
+        let execcfg = BestExecSettings::builder()
+            .intercept(true)
+            .noexec(true)
+            .build();
+        assert!(setup_caps(&execcfg).is_empty());
+        assert_eq!(rlimit_value(SRLimitValue::Unlimited), RLIM_INFINITY);
+        assert_eq!(rlimit_value(SRLimitValue::Limited(64)), 64);
+    }
+
+    #[test]
+    fn test_working_dir() {
+        let user = Cred::builder().build();
+        let root = User::from_uid(0.into()).unwrap().unwrap();
+        let execcfg = |cwd: Option<SCwd>, chroot: Option<&str>| {
+            BestExecSettings::builder()
+                .cred(CredOwnedData::builder().setuid(root.clone()).build())
+                .maybe_cwd(cwd)
+                .maybe_chroot(chroot.map(str::to_string))
+                .build()
+        };
+        let args = getopt(vec!["sr", "ls"]).unwrap();
+        assert_eq!(working_dir(&execcfg(None, None), &args, &user), Ok(None));
+        assert_eq!(
+            working_dir(&execcfg(None, Some("/jail")), &args, &user),
+            Ok(Some(PathBuf::from("/")))
+        );
+        assert_eq!(
+            working_dir(
+                &execcfg(Some(SCwd::Path("~/www".to_string())), None),
+                &args,
+                &user
+            ),
+            Ok(Some(root.dir.join("www")))
+        );
+        let args = getopt(vec!["sr", "-D", "/tmp", "ls"]).unwrap();
+        assert_eq!(args.chdir, Some(PathBuf::from("/tmp")));
+        assert_eq!(
+            working_dir(&execcfg(Some(SCwd::Any), None), &args, &user),
+            Ok(Some(PathBuf::from("/tmp")))
+        );
+        assert_eq!(
+            working_dir(
+                &execcfg(Some(SCwd::Path("/srv".to_string())), None),
+                &args,
+                &user
+            ),
+            Err(SrError::PermissionDenied)
+        );
+        let args = getopt(vec!["sr", "--chdir", "tmp", "ls"]).unwrap();
+        assert_eq!(
+            working_dir(&execcfg(Some(SCwd::Any), None), &args, &user),
+            Err(SrError::InvalidAgruments)
+        );
+    }
+
+    #[test]
+    fn test_getopt_list() {
+        let args = getopt(vec!["sr", "-l", "--user", "root"]).unwrap();
+        assert!(args.list);
+        assert_eq!(args.list_user, Some("root".into()));
+        assert!(args.opt_filter.unwrap().user.is_none());
+        let args = getopt(vec!["sr", "--user", "root", "ls"]).unwrap();
+        assert!(!args.list);
+        assert!(args.list_user.is_none());
+    }
+
+    #[test]
+    fn test_getopt_replay() {
+        let args = getopt(vec!["sr", "--replay", "root-1-2", "--dump"]).unwrap();
+        assert_eq!(args.replay, Some("root-1-2".to_string()));
+        assert!(args.dump);
+        assert!(getopt(vec!["sr", "--replay"]).is_err());
+    }
+
     #[test]
     fn test_make_cred() {
         let user = make_cred();
@@ -657,7 +1853,11 @@ mod tests {
             execcfg.bounding = SBounding::Strict;
             set_capabilities(&execcfg).unwrap();
             let capset = CapState::get_current().unwrap();
-            assert!(!capset.permitted.has(Cap::SETUID));
+            assert!(!capset.permitted.has(Cap::SETPCAP));
+            // kept by dosr to close the PAM session, never given to the command
+            assert!(capset.permitted.has(Cap::SETUID));
+            assert!(!capset.inheritable.has(Cap::SETUID));
+            assert!(!capctl::ambient::probe().unwrap().has(Cap::SETUID));
         }
     }
 
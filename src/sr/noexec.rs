@@ -0,0 +1,45 @@
+//! NOEXEC mode, the command cannot execute other programs, e.g. a shell escape of `less` or `vi`.
+//! dosr executes the command itself at the end of the pre-exec hook (see `exec`), once a seccomp filter
+//! makes `execveat` fail, and `execve` fail unless its path is the address of the command path in dosr.
+
+use libseccomp::{
+    error::SeccompError, ScmpAction, ScmpArgCompare, ScmpCompareOp, ScmpFilterContext, ScmpSyscall,
+};
+use log::error;
+
+use crate::exec::Exec;
+
+/// Denies every exec but the one of the command, whose path argument is at `path`
+fn filter(path: u64) -> Result<ScmpFilterContext, SeccompError> {
+    let mut ctx = ScmpFilterContext::new(ScmpAction::Allow)?;
+    ctx.add_rule_conditional(
+        ScmpAction::Errno(libc::EACCES),
+        ScmpSyscall::from_name("execve")?,
+        &[ScmpArgCompare::new(0, ScmpCompareOp::NotEqual, path)],
+    )?;
+    ctx.add_rule(
+        ScmpAction::Errno(libc::EACCES),
+        ScmpSyscall::from_name("execveat")?,
+    )?;
+    Ok(ctx)
+}
+
+/// Loads the filter in the command, before its exec
+pub fn install(exec: &Exec) -> std::io::Result<()> {
+    filter(exec.path_address())
+        .and_then(|ctx| ctx.load())
+        .map_err(|e| {
+            error!("Unable to load the noexec filter: {}", e);
+            std::io::Error::other("Unable to load the noexec filter")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        assert!(filter(0x1000).is_ok());
+    }
+}
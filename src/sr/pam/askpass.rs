@@ -0,0 +1,88 @@
+//! Password prompt through an askpass program, for callers without a terminal.
+//! The program comes from the global options only, never from the environment,
+//! and runs with the ids of the caller, without capabilities.
+
+use std::{
+    io,
+    os::{fd::AsRawFd, unix::process::CommandExt},
+    path::Path,
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use capctl::CapState;
+use log::{debug, error};
+use nix::unistd::{getgid, getuid, setresgid, setresuid};
+
+use super::{
+    rpassword::{read_unbuffered, wait_readable},
+    securemem::PamBuffer,
+};
+
+/// Runs `program` with the `prompt` as argument, it prints the answer on its first line
+pub(super) fn ask(
+    program: &Path,
+    prompt: &str,
+    timeout: Option<Duration>,
+) -> io::Result<PamBuffer> {
+    let (uid, gid) = (getuid(), getgid());
+    let mut command = Command::new(program);
+    command
+        .arg(prompt)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit());
+    unsafe {
+        command.pre_exec(move || {
+            setresgid(gid, gid, gid)?;
+            setresuid(uid, uid, uid)?;
+            capctl::ambient::clear()?;
+            CapState::empty().set_current()?;
+            capctl::prctl::set_no_new_privs()?;
+            Ok(())
+        });
+    }
+    debug!("Running askpass program {}", program.display());
+    let mut child = command.spawn()?;
+    let mut stdout = child.stdout.take().expect("askpass stdout is piped");
+    let answer =
+        wait_readable(stdout.as_raw_fd(), timeout).and_then(|_| read_unbuffered(&mut stdout));
+    drop(stdout);
+    if answer.is_err() {
+        let _ = child.kill();
+    }
+    let status = child.wait()?;
+    let answer = answer?;
+    if !status.success() {
+        error!("Askpass program {} failed: {}", program.display(), status);
+        return Err(io::Error::other("askpass program failed"));
+    }
+    Ok(answer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(buffer: &PamBuffer) -> String {
+        buffer
+            .iter()
+            .map(|&b| b as char)
+            .take_while(|&c| c != '\0')
+            .collect()
+    }
+
+    #[test]
+    fn test_ask() {
+        let password = ask(Path::new("/bin/echo"), "secret", None).unwrap();
+        assert_eq!(answer(&password), "secret");
+        assert!(ask(Path::new("/bin/false"), "Password: ", None).is_err());
+        assert!(ask(Path::new("/nonexistent/askpass"), "Password: ", None).is_err());
+        assert!(ask(
+            Path::new("/bin/sleep"),
+            "5",
+            Some(Duration::from_millis(10))
+        )
+        .is_err_and(|e| e.kind() == io::ErrorKind::TimedOut));
+    }
+}
@@ -1,11 +1,9 @@
-use std::{borrow::Cow, ffi::CStr, ops::Deref};
+use std::{borrow::Cow, ffi::CStr, io::ErrorKind, ops::Deref, path::Path, time::Duration};
 
 use bon::Builder;
 use log::{debug, error, info, warn};
-use nonstick::{
-    AuthnFlags, ConversationAdapter, ErrorCode, Result as PamResult, Transaction,
-    TransactionBuilder,
-};
+use nix::unistd::User;
+use nonstick::{ConversationAdapter, ErrorCode, Result as PamResult};
 use pcre2::bytes::RegexBuilder;
 
 #[cfg(feature = "timeout")]
@@ -15,32 +13,46 @@ use crate::{
     Cli,
 };
 use rar_common::{
-    database::options::{SAuthentication, STimeout},
+    database::options::{SAuthPolicy, SAuthUser, STimeout},
+    util::{create_dir_all_with_privileges, create_with_privileges},
     Cred,
 };
 
-use self::rpassword::Terminal;
+use self::{rpassword::Terminal, securemem::PamBuffer};
 
+mod askpass;
 #[allow(dead_code, reason = "This file is part of sudo-rs.")]
 mod cutils;
 #[allow(dead_code, reason = "This file is part of sudo-rs.")]
 mod rpassword;
 #[allow(dead_code, reason = "This file is part of sudo-rs.")]
 mod securemem;
+mod session;
+
+pub(crate) use self::session::{Session, SESSION_CAPS};
 
 const PAM_SERVICE: &str = env!("RAR_PAM_SERVICE");
-pub(crate) const PAM_PROMPT: &str = "Password: ";
+const PAM_PROMPT: &str = "Password: ";
+
+#[cfg(not(test))]
+const LECTURE_LOCATION: &str = env!("RAR_LECTURE_STORAGE");
+#[cfg(test)]
+const LECTURE_LOCATION: &str = "target/lectured";
 
 #[derive(Builder)]
 struct SrConversationHandler<'a> {
     #[builder(into)]
     username: Option<Cow<'a, str>>,
-    #[builder(default = "Password: ", into)]
+    #[builder(default = PAM_PROMPT, into)]
     prompt: Cow<'a, str>,
     #[builder(default)]
     use_stdin: bool,
     #[builder(default)]
     no_interact: bool,
+    /// Maximum time to wait for the user to start typing
+    timeout: Option<Duration>,
+    /// Program asking the password instead of the terminal
+    askpass: Option<&'a Path>,
 }
 
 impl SrConversationHandler<'_> {
@@ -51,6 +63,51 @@ impl SrConversationHandler<'_> {
             Terminal::open_tty()
         }
     }
+    /// Asks through the askpass program if any, else through the terminal
+    fn ask(&self, prompt: &str, masked: bool) -> PamResult<PamBuffer> {
+        if let Some(program) = self.askpass {
+            return askpass::ask(program, prompt, self.timeout).map_err(|e| self.read_error(e));
+        }
+        let mut term = self.open().map_err(|_| ErrorCode::ConversationError)?;
+        term.prompt(&prompt)
+            .map_err(|_| ErrorCode::ConversationError)?;
+        if masked {
+            term.read_password(self.timeout)
+        } else {
+            term.read_cleartext(self.timeout)
+        }
+        .map_err(|e| self.read_error(e))
+    }
+    fn read_error(&self, e: std::io::Error) -> ErrorCode {
+        if e.kind() == ErrorKind::TimedOut {
+            self.error_msg("Password prompt timed out");
+            ErrorCode::ConversationError
+        } else {
+            ErrorCode::BufferError
+        }
+    }
+    /// Shows the lecture once per user, before the first password prompt
+    fn lecture(&self, user: &Cred, lecture: &str) {
+        if self.no_interact {
+            return;
+        }
+        let marker = Path::new(LECTURE_LOCATION).join(user.user.uid.as_raw().to_string());
+        if marker.exists() {
+            return;
+        }
+        if let Err(e) = self
+            .open()
+            .and_then(|mut term| term.prompt(&format!("{}\n", lecture.trim_end())))
+        {
+            warn!("Unable to show the lecture: {}", e);
+            return;
+        }
+        if let Err(e) = create_dir_all_with_privileges(LECTURE_LOCATION)
+            .and_then(|_| create_with_privileges(&marker))
+        {
+            warn!("Unable to remember the lecture: {}", e);
+        }
+    }
     fn is_pam_password_prompt(&self, prompt: &impl AsRef<str>) -> bool {
         let pam_prompt = prompt.as_ref();
         RegexBuilder::new()
@@ -71,10 +128,12 @@ impl SrConversationHandler<'_> {
 impl Default for SrConversationHandler<'_> {
     fn default() -> Self {
         SrConversationHandler {
-            prompt: "Password: ".into(),
+            prompt: PAM_PROMPT.into(),
             username: None,
             use_stdin: false,
             no_interact: false,
+            timeout: None,
+            askpass: None,
         }
     }
 }
@@ -84,10 +143,7 @@ impl ConversationAdapter for SrConversationHandler<'_> {
         if self.no_interact {
             return Err(ErrorCode::ConversationError);
         }
-        let mut term = self.open().map_err(|_| ErrorCode::ConversationError)?;
-        term.prompt(&prompt.as_ref().to_string_lossy().to_string())
-            .map_err(|_| ErrorCode::ConversationError)?;
-        let read = term.read_cleartext().map_err(|_| ErrorCode::BufferError)?;
+        let read = self.ask(&prompt.as_ref().to_string_lossy(), false)?;
         Ok(std::ffi::OsString::from(
             String::from_utf8_lossy(read.deref()).to_string(),
         ))
@@ -103,10 +159,7 @@ impl ConversationAdapter for SrConversationHandler<'_> {
         } else {
             prompt.as_ref().to_string_lossy()
         };
-        let mut term = self.open().map_err(|_| ErrorCode::ConversationError)?;
-        term.prompt(&pam_prompt)
-            .map_err(|_| ErrorCode::ConversationError)?;
-        let read = term.read_password().map_err(|_| ErrorCode::BufferError)?;
+        let read = self.ask(&pam_prompt, true)?;
         let os_str = CStr::from_bytes_until_nul(read.deref()).unwrap();
         Ok(std::ffi::OsString::from(os_str.to_str().unwrap()))
     }
@@ -122,48 +175,112 @@ impl ConversationAdapter for SrConversationHandler<'_> {
     }
 }
 
+/// PAM service of the task, the default one when the policy does not name it
+pub(super) fn service(authentication: &SAuthPolicy) -> &str {
+    authentication.service.as_deref().unwrap_or(PAM_SERVICE)
+}
+
+/// Account whose password is asked, `target` is the user the command runs as, if any
+fn auth_user(authentication: &SAuthPolicy, user: &Cred, target: Option<&User>) -> SrResult<User> {
+    match &authentication.user {
+        None | Some(SAuthUser::Caller) => Ok(user.user.clone()),
+        Some(SAuthUser::Target) => Ok(target.unwrap_or(&user.user).clone()),
+        Some(SAuthUser::Account(account)) => account.fetch_user().ok_or_else(|| {
+            error!("Authentication account {} does not exist", account);
+            SrError::ConfigurationError
+        }),
+    }
+}
+
+/// Authenticates the user unless the task skips it or a cookie is still valid,
+/// the transaction that authenticated the user is returned to open the session
 pub(super) fn check_auth(
-    authentication: &SAuthentication,
+    authentication: &SAuthPolicy,
     #[cfg_attr(not(feature = "timeout"), allow(unused_variables))] timeout: &STimeout,
     user: &Cred,
+    target: Option<&User>,
+    askpass: Option<&Path>,
     cli: &Cli,
-) -> SrResult<()> {
-    if authentication.is_skip() {
+) -> SrResult<Option<Session>> {
+    if authentication.mode.is_skip() {
         warn!("Skipping authentication, this is a security risk!");
-        return Ok(());
+        return Ok(None);
     }
+    let auth_user = auth_user(authentication, user, target)?;
     #[cfg(feature = "timeout")]
-    let is_valid = timeout::is_valid(user, user, timeout);
+    let is_valid = timeout::is_valid(user, &auth_user, timeout);
     #[cfg(not(feature = "timeout"))]
     let is_valid = false;
     debug!("need to re-authenticate : {}", !is_valid);
+    if !is_valid && cli.non_interactive {
+        error!("A password is required, prompting is disabled by --non-interactive");
+        return Err(SrError::InteractionRequired);
+    }
+    let mut session = None;
     if !is_valid {
+        let askpass = match askpass {
+            None if cli.askpass => {
+                error!("No askpass program is set in the global options");
+                return Err(SrError::ConfigurationError);
+            }
+            // a relative program would be looked up in the PATH of the caller
+            Some(program) if cli.askpass && !program.is_absolute() => {
+                error!(
+                    "The askpass program {} is not an absolute path",
+                    program.display()
+                );
+                return Err(SrError::ConfigurationError);
+            }
+            askpass => askpass.filter(|_| cli.askpass),
+        };
+        // tell whose password is asked when it is not the caller's one
+        let prompt = match cli.prompt.as_deref().or(authentication.prompt.as_deref()) {
+            Some(prompt) => Cow::Borrowed(prompt),
+            None if auth_user.uid != user.user.uid => {
+                Cow::Owned(format!("{}'s {}", auth_user.name, PAM_PROMPT))
+            }
+            None => Cow::Borrowed(PAM_PROMPT),
+        };
         let conv = SrConversationHandler::builder()
-            .maybe_prompt(cli.prompt.as_ref().map(|s| Cow::Borrowed(s.as_str())))
+            .username(auth_user.name.as_str())
+            .prompt(prompt)
             .use_stdin(cli.stdin)
+            .maybe_askpass(askpass)
+            .maybe_timeout(authentication.prompt_timeout.and_then(|d| d.to_std().ok()))
             .build();
-        let mut txn = TransactionBuilder::new_with_service(PAM_SERVICE)
-            .username(&user.user.name)
-            .build(conv.into_conversation())
-            .map_err(|e| {
-                error!("Failed to create PAM transaction: {}", e);
-                SrError::SystemError
-            })?;
-        txn.authenticate(AuthnFlags::SILENT).map_err(|e| {
-            error!("Authentication failed: {}", e);
-            SrError::AuthenticationFailed
-        })?;
-        txn.account_management(AuthnFlags::SILENT).map_err(|e| {
+        if let Some(lecture) = &authentication.lecture {
+            conv.lecture(user, lecture);
+        }
+        let txn = session.insert(Session::start(
+            service(authentication),
+            &auth_user.name,
+            &user.user.name,
+        )?);
+        let mut retries = authentication.retries.unwrap_or_default();
+        loop {
+            match txn.authenticate(&conv) {
+                Ok(()) => break,
+                Err(ErrorCode::AuthenticationError) if retries > 0 => {
+                    retries -= 1;
+                    eprintln!("Sorry, try again.");
+                }
+                Err(e) => {
+                    error!("Authentication failed: {}", e);
+                    return Err(SrError::AuthenticationFailed);
+                }
+            }
+        }
+        txn.account_management(&conv).map_err(|e| {
             error!("Account management failed: {}", e);
             SrError::AuthenticationFailed
         })?;
     }
     #[cfg(feature = "timeout")]
-    timeout::update_cookie(user, user, timeout).map_err(|e| {
+    timeout::update_cookie(user, &auth_user, timeout).map_err(|e| {
         error!("Failed to update timeout cookie: {}", e);
         SrError::SystemError
     })?;
-    Ok(())
+    Ok(session)
 }
 
 #[cfg(test)]
@@ -258,9 +375,124 @@ mod tests {
         handler.info_msg(OsStr::new("Test info message"));
     }
 
+    #[test]
+    fn test_lecture_once() {
+        let user = create_test_user();
+        let marker = Path::new(LECTURE_LOCATION).join(user.user.uid.as_raw().to_string());
+        let _ = std::fs::remove_file(&marker);
+        let handler = SrConversationHandler::builder().use_stdin(true).build();
+        handler.lecture(&user, "With great power comes great responsibility.");
+        assert!(marker.exists());
+        handler.lecture(&user, "With great power comes great responsibility.");
+        assert!(marker.exists());
+    }
+
+    #[test]
+    fn test_service() {
+        assert_eq!(service(&SAuthentication::Perform.into()), PAM_SERVICE);
+        let policy = SAuthPolicy::builder().service("dosr-admin").build();
+        assert_eq!(service(&policy), "dosr-admin");
+    }
+
+    #[test]
+    fn test_check_auth_non_interactive() {
+        let user = create_test_user();
+        // a cookie is never valid without usage left
+        let timeout = STimeout {
+            max_usage: Some(0),
+            ..create_test_timeout()
+        };
+        let cli = Cli::builder().non_interactive().build();
+        assert!(matches!(
+            check_auth(
+                &SAuthentication::Perform.into(),
+                &timeout,
+                &user,
+                None,
+                None,
+                &cli
+            ),
+            Err(SrError::InteractionRequired)
+        ));
+        assert!(check_auth(
+            &SAuthentication::Skip.into(),
+            &timeout,
+            &user,
+            None,
+            None,
+            &cli
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_check_auth_askpass() {
+        let user = create_test_user();
+        let timeout = STimeout {
+            max_usage: Some(0),
+            ..create_test_timeout()
+        };
+        let cli = Cli::builder().askpass().build();
+        for askpass in [None, Some(Path::new("ssh-askpass"))] {
+            assert!(matches!(
+                check_auth(
+                    &SAuthentication::Perform.into(),
+                    &timeout,
+                    &user,
+                    None,
+                    askpass,
+                    &cli
+                ),
+                Err(SrError::ConfigurationError)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_auth_user() {
+        let user = create_test_user();
+        let root = User::from_uid(0.into()).unwrap().unwrap();
+        let policy = |auth_user| SAuthPolicy {
+            user: Some(auth_user),
+            ..Default::default()
+        };
+        assert_eq!(
+            auth_user(&SAuthPolicy::default(), &user, Some(&root))
+                .unwrap()
+                .uid,
+            user.user.uid
+        );
+        assert_eq!(
+            auth_user(&policy(SAuthUser::Target), &user, Some(&root))
+                .unwrap()
+                .uid,
+            root.uid
+        );
+        assert_eq!(
+            auth_user(&policy(SAuthUser::Target), &user, None)
+                .unwrap()
+                .uid,
+            user.user.uid
+        );
+        assert_eq!(
+            auth_user(&policy(SAuthUser::Account("root".into())), &user, None)
+                .unwrap()
+                .uid,
+            root.uid
+        );
+        assert!(matches!(
+            auth_user(
+                &policy(SAuthUser::Account("nonexistent-rar-user".into())),
+                &user,
+                None
+            ),
+            Err(SrError::ConfigurationError)
+        ));
+    }
+
     #[test]
     fn test_check_auth_skip_authentication() {
-        let authentication = SAuthentication::Skip;
+        let authentication = SAuthentication::Skip.into();
         let timeout = create_test_timeout();
         let user = create_test_user();
 
@@ -269,6 +501,8 @@ mod tests {
             &authentication,
             &timeout,
             &user,
+            None,
+            None,
             &Cli::builder().prompt("Password: ").build(),
         );
         assert!(result.is_ok());
@@ -280,7 +514,7 @@ mod tests {
             println!("Skipping test_check_auth_required_but_valid_timeout because RAR_PAM_SERVICE is set to original dosr");
             return;
         }
-        let authentication = SAuthentication::Perform;
+        let authentication = SAuthentication::Perform.into();
         let timeout = create_test_timeout();
         let user = create_test_user();
 
@@ -288,6 +522,8 @@ mod tests {
             &authentication,
             &timeout,
             &user,
+            None,
+            None,
             &Cli::builder().prompt("Password: ").build(),
         );
     }
@@ -369,13 +605,15 @@ mod tests {
         };
 
         let user = create_test_user();
-        let auth = SAuthentication::Skip;
+        let auth = SAuthentication::Skip.into();
 
         // Test different timeout types don't cause errors
         assert!(check_auth(
             &auth,
             &timeout_ppid,
             &user,
+            None,
+            None,
             &Cli::builder().prompt("Password: ").build()
         )
         .is_ok());
@@ -383,6 +621,8 @@ mod tests {
             &auth,
             &timeout_tty,
             &user,
+            None,
+            None,
             &Cli::builder().prompt("Password: ").build()
         )
         .is_ok());
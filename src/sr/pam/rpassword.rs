@@ -16,9 +16,10 @@
 ///
 use std::io::{self, Error, ErrorKind, Read};
 use std::os::fd::{AsRawFd, RawFd};
+use std::time::Duration;
 use std::{fs, mem};
 
-use libc::{tcsetattr, termios, ECHO, ECHONL, TCSANOW};
+use libc::{pollfd, tcsetattr, termios, ECHO, ECHONL, POLLIN, TCSANOW};
 
 use super::cutils::cerr;
 
@@ -77,7 +78,7 @@ fn safe_tcgetattr(fd: RawFd) -> io::Result<termios> {
 }
 
 /// Reads a password from the given file descriptor
-fn read_unbuffered(source: &mut impl io::Read) -> io::Result<PamBuffer> {
+pub(super) fn read_unbuffered(source: &mut impl io::Read) -> io::Result<PamBuffer> {
     let mut password = PamBuffer::default();
     let mut pwd_iter = password.iter_mut();
 
@@ -99,6 +100,23 @@ fn read_unbuffered(source: &mut impl io::Read) -> io::Result<PamBuffer> {
     Ok(password)
 }
 
+/// Waits until the file descriptor has something to read, fails with `TimedOut`
+pub(super) fn wait_readable(fd: RawFd, timeout: Option<Duration>) -> io::Result<()> {
+    let Some(timeout) = timeout else {
+        return Ok(());
+    };
+    let mut fd = pollfd {
+        fd,
+        events: POLLIN,
+        revents: 0,
+    };
+    let millis = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+    if cerr(unsafe { libc::poll(&mut fd, 1, millis) })? == 0 {
+        return Err(Error::new(ErrorKind::TimedOut, "password prompt timed out"));
+    }
+    Ok(())
+}
+
 /// Write something and immediately flush
 fn write_unbuffered(sink: &mut impl io::Write, text: &str) -> io::Result<()> {
     sink.write_all(text.as_bytes())?;
@@ -130,17 +148,26 @@ impl Terminal<'_> {
 
     /// Reads input with TTY echo disabled
     #[cfg_attr(tarpaulin, ignore)]
-    pub fn read_password(&mut self) -> io::Result<PamBuffer> {
-        let mut input = self.source();
+    pub fn read_password(&mut self, timeout: Option<Duration>) -> io::Result<PamBuffer> {
         let _hide_input = HiddenInput::new()?;
-        read_unbuffered(&mut input)
+        self.wait_input(timeout)?;
+        read_unbuffered(&mut self.source())
     }
 
     /// Reads input with TTY echo enabled
-    pub fn read_cleartext(&mut self) -> io::Result<PamBuffer> {
+    pub fn read_cleartext(&mut self, timeout: Option<Duration>) -> io::Result<PamBuffer> {
+        self.wait_input(timeout)?;
         read_unbuffered(&mut self.source())
     }
 
+    /// Waits until the user starts typing, standard input is buffered so it is never timed out
+    fn wait_input(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Terminal::Tty(tty) => wait_readable(tty.as_raw_fd(), timeout),
+            Terminal::StdIE(..) => Ok(()),
+        }
+    }
+
     /// Display information
     pub fn prompt(&mut self, text: &impl AsRef<str>) -> io::Result<()> {
         write_unbuffered(&mut self.sink(), text.as_ref())
@@ -166,6 +193,7 @@ impl Terminal<'_> {
 mod test {
     use super::{read_unbuffered, write_unbuffered, Terminal};
     use std::io::{self, Read, Write};
+    use std::time::Duration;
 
     #[test]
     fn miri_test_read() {
@@ -239,6 +267,19 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_terminal_read_timeout() {
+        let (read, mut write) = nix::unistd::pipe().unwrap();
+        let mut terminal = Terminal::Tty(read.into());
+        assert!(terminal
+            .read_cleartext(Some(Duration::from_millis(10)))
+            .is_err_and(|e| e.kind() == io::ErrorKind::TimedOut));
+        nix::unistd::write(&mut write, b"typed\n").unwrap();
+        assert!(terminal
+            .read_cleartext(Some(Duration::from_millis(10)))
+            .is_ok());
+    }
+
     #[test]
     fn test_terminal_open_stdie() {
         // Test that open_stdie always succeeds
@@ -0,0 +1,421 @@
+//! PAM transaction of dosr, from the authentication to the end of the command.
+//! The transaction that authenticated the user establishes the credentials and opens the session
+//! of the target user before the command starts, so session modules (pam_limits, pam_systemd,
+//! pam_keyinit, pam_env) apply to it; the session is closed and the credentials deleted once the command exits.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    ffi::{c_int, c_void, CStr, CString, OsStr},
+    io::stdin,
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    ptr,
+};
+
+use capctl::Cap;
+use libpam_sys::{
+    pam_acct_mgmt, pam_authenticate, pam_close_session, pam_conv, pam_end, pam_getenvlist,
+    pam_handle, pam_message, pam_open_session, pam_response, pam_set_item, pam_setcred, pam_start,
+    pam_strerror, PAM_BUF_ERR, PAM_CONV, PAM_CONV_ERR, PAM_DELETE_CRED, PAM_ERROR_MSG,
+    PAM_ESTABLISH_CRED, PAM_PROMPT_ECHO_OFF, PAM_PROMPT_ECHO_ON, PAM_RUSER, PAM_SILENT,
+    PAM_SUCCESS, PAM_TEXT_INFO, PAM_TTY, PAM_USER,
+};
+use log::{debug, error, info, warn};
+use nix::unistd::ttyname;
+use nonstick::{constants::ReturnCode, ConversationAdapter, ErrorCode, Result as PamResult};
+use rar_common::util::{initialize_capabilities, stated_drop_effective};
+
+use crate::error::{SrError, SrResult};
+
+/// Session modules may switch ids (pam_keyinit), raise limits (pam_limits) and write audit records,
+/// dosr keeps them until the session is closed
+pub(crate) const SESSION_CAPS: [Cap; 5] = [
+    Cap::SETUID,
+    Cap::SETGID,
+    Cap::SYS_RESOURCE,
+    Cap::AUDIT_WRITE,
+    Cap::DAC_READ_SEARCH,
+];
+
+/// Session modules have nothing to ask once the user is authenticated, they only send messages
+unsafe extern "C" fn session_conversation(
+    num_msg: c_int,
+    msg: *const *const pam_message,
+    resp: *mut *mut pam_response,
+    _appdata: *mut c_void,
+) -> c_int {
+    if num_msg <= 0 || msg.is_null() || resp.is_null() {
+        return PAM_CONV_ERR;
+    }
+    for i in 0..num_msg as usize {
+        let message = &**msg.add(i);
+        let text = if message.msg.is_null() {
+            Cow::Borrowed("")
+        } else {
+            CStr::from_ptr(message.msg).to_string_lossy()
+        };
+        match message.msg_style {
+            PAM_ERROR_MSG => {
+                error!("{}", text);
+                eprintln!("{}", text);
+            }
+            PAM_TEXT_INFO => {
+                info!("{}", text);
+                println!("{}", text);
+            }
+            _ => {
+                warn!("PAM session module prompted: {}", text);
+                return PAM_CONV_ERR;
+            }
+        }
+    }
+    // PAM frees the responses, they must come from malloc
+    let responses = libc::calloc(num_msg as usize, size_of::<pam_response>()) as *mut pam_response;
+    if responses.is_null() {
+        return PAM_BUF_ERR;
+    }
+    *resp = responses;
+    PAM_SUCCESS
+}
+
+/// Answers the prompts of the authentication modules with `appdata`, the `C` given to `Session::converse`
+unsafe extern "C" fn prompt_conversation<C: ConversationAdapter>(
+    num_msg: c_int,
+    msg: *const *const pam_message,
+    resp: *mut *mut pam_response,
+    appdata: *mut c_void,
+) -> c_int {
+    if num_msg <= 0 || msg.is_null() || resp.is_null() || appdata.is_null() {
+        return PAM_CONV_ERR;
+    }
+    let conv = &*(appdata as *const C);
+    // PAM frees the responses, they must come from malloc
+    let responses = libc::calloc(num_msg as usize, size_of::<pam_response>()) as *mut pam_response;
+    if responses.is_null() {
+        return PAM_BUF_ERR;
+    }
+    for i in 0..num_msg as usize {
+        let message = &**msg.add(i);
+        let text = if message.msg.is_null() {
+            OsStr::new("")
+        } else {
+            OsStr::from_bytes(CStr::from_ptr(message.msg).to_bytes())
+        };
+        let answer = match message.msg_style {
+            PAM_PROMPT_ECHO_OFF => conv.masked_prompt(text).map(Some),
+            PAM_PROMPT_ECHO_ON => conv.prompt(text).map(Some),
+            PAM_ERROR_MSG => {
+                conv.error_msg(text);
+                Ok(None)
+            }
+            PAM_TEXT_INFO => {
+                conv.info_msg(text);
+                Ok(None)
+            }
+            _ => Err(ErrorCode::ConversationError),
+        };
+        let ret = match answer.map(|a| a.map(|a| CString::new(a.into_vec()))) {
+            Ok(None) => PAM_SUCCESS,
+            Ok(Some(Ok(answer))) => {
+                let answer = libc::strdup(answer.as_ptr());
+                (*responses.add(i)).resp = answer;
+                if answer.is_null() {
+                    PAM_BUF_ERR
+                } else {
+                    PAM_SUCCESS
+                }
+            }
+            Ok(Some(Err(_))) | Err(_) => PAM_CONV_ERR,
+        };
+        if ret != PAM_SUCCESS {
+            free_responses(responses, i);
+            return ret;
+        }
+    }
+    *resp = responses;
+    PAM_SUCCESS
+}
+
+/// Frees the responses up to `last`, the answers are erased first
+unsafe fn free_responses(responses: *mut pam_response, last: usize) {
+    for i in 0..=last {
+        let answer = (*responses.add(i)).resp;
+        if !answer.is_null() {
+            libc::explicit_bzero(answer as *mut c_void, libc::strlen(answer));
+            libc::free(answer as *mut c_void);
+        }
+    }
+    libc::free(responses as *mut c_void);
+}
+
+pub(crate) struct Session {
+    handle: *mut pam_handle,
+    /// Some PAM implementations keep a pointer to the conversation
+    conv: Box<pam_conv>,
+    established: bool,
+    opened: bool,
+    status: c_int,
+}
+
+impl Session {
+    /// Starts the transaction of `user` with the `service` policy, requested by `ruser` from the terminal of dosr
+    pub(crate) fn start(service: &str, user: &str, ruser: &str) -> SrResult<Self> {
+        let service = cstring(service)?;
+        let user = cstring(user)?;
+        let mut conv = Box::new(pam_conv {
+            conv: session_conversation,
+            appdata_ptr: ptr::null_mut(),
+        });
+        let mut handle = ptr::null_mut();
+        let ret = unsafe { pam_start(service.as_ptr(), user.as_ptr(), conv.as_mut(), &mut handle) };
+        if ret != PAM_SUCCESS || handle.is_null() {
+            error!("Failed to start PAM transaction: error {}", ret);
+            return Err(SrError::SystemError);
+        }
+        let mut session = Session {
+            handle,
+            conv,
+            established: false,
+            opened: false,
+            status: PAM_SUCCESS,
+        };
+        session.set_item(PAM_RUSER, ruser)?;
+        if let Some(tty) = ttyname(stdin())
+            .ok()
+            .as_deref()
+            .and_then(|tty| tty.to_str())
+        {
+            session.set_item(PAM_TTY, tty)?;
+        }
+        Ok(session)
+    }
+
+    /// Authenticates the user of the transaction, `conv` answers the prompts
+    pub(crate) fn authenticate<C: ConversationAdapter>(&mut self, conv: &C) -> PamResult<()> {
+        self.converse(conv, |h| unsafe { pam_authenticate(h, PAM_SILENT) })
+    }
+
+    /// Checks that the account of the authenticated user is valid, e.g. not expired
+    pub(crate) fn account_management<C: ConversationAdapter>(&mut self, conv: &C) -> PamResult<()> {
+        self.converse(conv, |h| unsafe { pam_acct_mgmt(h, PAM_SILENT) })
+    }
+
+    /// Opens the session of `user`, who may not be the authenticated one, like sudo does
+    pub(crate) fn open(&mut self, user: &str) -> SrResult<()> {
+        self.set_item(PAM_USER, user)?;
+        self.call("pam_setcred", |h| unsafe {
+            pam_setcred(h, PAM_ESTABLISH_CRED)
+        })?;
+        self.established = true;
+        self.call("pam_open_session", |h| unsafe { pam_open_session(h, 0) })?;
+        self.opened = true;
+        debug!("PAM session opened");
+        Ok(())
+    }
+
+    /// Variables set by the session modules, e.g. by pam_env or pam_systemd
+    pub(crate) fn env(&self) -> HashMap<String, String> {
+        let mut env = HashMap::new();
+        let list = unsafe { pam_getenvlist(self.handle) };
+        if list.is_null() {
+            return env;
+        }
+        let mut i = 0;
+        loop {
+            let entry = unsafe { *list.add(i) };
+            if entry.is_null() {
+                break;
+            }
+            if let Some((key, value)) = unsafe { CStr::from_ptr(entry) }
+                .to_string_lossy()
+                .split_once('=')
+            {
+                env.insert(key.to_string(), value.to_string());
+            }
+            unsafe { libc::free(entry as *mut c_void) };
+            i += 1;
+        }
+        unsafe { libc::free(list as *mut c_void) };
+        env
+    }
+
+    fn set_item(&mut self, item: c_int, value: &str) -> SrResult<()> {
+        let value = cstring(value)?;
+        let ret = unsafe { pam_set_item(self.handle, item, value.as_ptr() as *const c_void) };
+        self.check("pam_set_item", ret)
+    }
+
+    /// Calls a PAM function with `conv` answering the prompts, the session conversation is restored after
+    fn converse<C: ConversationAdapter>(
+        &mut self,
+        conv: &C,
+        f: impl FnOnce(*mut pam_handle) -> c_int,
+    ) -> PamResult<()> {
+        let prompting = pam_conv {
+            conv: prompt_conversation::<C>,
+            appdata_ptr: conv as *const C as *mut c_void,
+        };
+        let ret = unsafe {
+            pam_set_item(
+                self.handle,
+                PAM_CONV,
+                &prompting as *const pam_conv as *const c_void,
+            )
+        };
+        let ret = if ret == PAM_SUCCESS {
+            f(self.handle)
+        } else {
+            ret
+        };
+        unsafe {
+            pam_set_item(
+                self.handle,
+                PAM_CONV,
+                self.conv.as_ref() as *const pam_conv as *const c_void,
+            )
+        };
+        if ret != PAM_SUCCESS {
+            self.status = ret;
+            return Err(ErrorCode::try_from(ReturnCode::from(ret)).unwrap_or(ErrorCode::BAD_CONST));
+        }
+        Ok(())
+    }
+
+    /// Calls a PAM function with the privileges of the session modules
+    fn call(&mut self, name: &str, f: impl FnOnce(*mut pam_handle) -> c_int) -> SrResult<()> {
+        // kept by dosr after its uid switch until the session is closed, see set_capabilities
+        let state = initialize_capabilities(&SESSION_CAPS)
+            .inspect_err(|e| {
+                error!(
+                    "Unable to raise the PAM session privileges, {} may fail: {}",
+                    name, e
+                )
+            })
+            .ok();
+        let ret = f(self.handle);
+        if let Some(state) = state {
+            stated_drop_effective(state)?;
+        }
+        self.check(name, ret)
+    }
+
+    fn check(&mut self, name: &str, ret: c_int) -> SrResult<()> {
+        if ret == PAM_SUCCESS {
+            return Ok(());
+        }
+        self.status = ret;
+        let message = unsafe { pam_strerror(self.handle, ret) };
+        if message.is_null() {
+            error!("{} failed: error {}", name, ret);
+        } else {
+            error!(
+                "{} failed: {}",
+                name,
+                unsafe { CStr::from_ptr(message) }.to_string_lossy()
+            );
+        }
+        Err(SrError::SystemError)
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if self.opened {
+            let _ = self.call("pam_close_session", |h| unsafe {
+                pam_close_session(h, PAM_SILENT)
+            });
+        }
+        if self.established {
+            let _ = self.call("pam_setcred", |h| unsafe {
+                pam_setcred(h, PAM_DELETE_CRED | PAM_SILENT)
+            });
+        }
+        unsafe { pam_end(self.handle, self.status) };
+        debug!("PAM session closed");
+    }
+}
+
+fn cstring(value: &str) -> SrResult<CString> {
+    CString::new(value).map_err(|_| {
+        error!("Invalid PAM item: {}", value);
+        SrError::InvalidAgruments
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_conversation() {
+        let text = CString::new("Welcome").unwrap();
+        let info = pam_message {
+            msg_style: PAM_TEXT_INFO,
+            msg: text.as_ptr(),
+        };
+        let prompt = pam_message {
+            msg_style: libpam_sys::PAM_PROMPT_ECHO_OFF,
+            msg: text.as_ptr(),
+        };
+        let mut resp: *mut pam_response = ptr::null_mut();
+        let messages = [&info as *const pam_message];
+        let ret = unsafe { session_conversation(1, messages.as_ptr(), &mut resp, ptr::null_mut()) };
+        assert_eq!(ret, PAM_SUCCESS);
+        assert!(!resp.is_null());
+        assert!(unsafe { (*resp).resp }.is_null());
+        unsafe { libc::free(resp as *mut c_void) };
+        let messages = [&info as *const pam_message, &prompt as *const pam_message];
+        let mut resp: *mut pam_response = ptr::null_mut();
+        let ret = unsafe { session_conversation(2, messages.as_ptr(), &mut resp, ptr::null_mut()) };
+        assert_eq!(ret, PAM_CONV_ERR);
+        assert!(resp.is_null());
+    }
+
+    struct Answer;
+
+    impl ConversationAdapter for Answer {
+        fn prompt(&self, _prompt: impl AsRef<OsStr>) -> PamResult<std::ffi::OsString> {
+            Err(ErrorCode::ConversationError)
+        }
+
+        fn masked_prompt(&self, _prompt: impl AsRef<OsStr>) -> PamResult<std::ffi::OsString> {
+            Ok("secret".into())
+        }
+
+        fn error_msg(&self, _message: impl AsRef<OsStr>) {}
+
+        fn info_msg(&self, _message: impl AsRef<OsStr>) {}
+    }
+
+    #[test]
+    fn test_prompt_conversation() {
+        let text = CString::new("Password: ").unwrap();
+        let info = pam_message {
+            msg_style: PAM_TEXT_INFO,
+            msg: text.as_ptr(),
+        };
+        let masked = pam_message {
+            msg_style: PAM_PROMPT_ECHO_OFF,
+            msg: text.as_ptr(),
+        };
+        let echo = pam_message {
+            msg_style: PAM_PROMPT_ECHO_ON,
+            msg: text.as_ptr(),
+        };
+        let appdata = &Answer as *const Answer as *mut c_void;
+        let messages = [&info as *const pam_message, &masked as *const pam_message];
+        let mut resp: *mut pam_response = ptr::null_mut();
+        let ret =
+            unsafe { prompt_conversation::<Answer>(2, messages.as_ptr(), &mut resp, appdata) };
+        assert_eq!(ret, PAM_SUCCESS);
+        assert!(unsafe { (*resp).resp }.is_null());
+        assert_eq!(unsafe { CStr::from_ptr((*resp.add(1)).resp) }, c"secret");
+        unsafe { free_responses(resp, 1) };
+        let messages = [&masked as *const pam_message, &echo as *const pam_message];
+        let mut resp: *mut pam_response = ptr::null_mut();
+        let ret =
+            unsafe { prompt_conversation::<Answer>(2, messages.as_ptr(), &mut resp, appdata) };
+        assert_eq!(ret, PAM_CONV_ERR);
+        assert!(resp.is_null());
+    }
+}
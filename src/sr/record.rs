@@ -0,0 +1,343 @@
+//! This module records the output of the executed command, so it can be replayed with `dosr --replay <id>`
+//! Only the output is recorded: the user input (passwords typed in the session) never reaches the log.
+//! Sessions are stored in a root only directory, so replay is gated by file permissions.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    os::unix::fs::{fchown, OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+    thread::sleep,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use capctl::Cap;
+use log::{debug, error, info};
+use rar_common::{
+    database::options::SRecord,
+    util::{create_dir_all_with_privileges, with_privileges},
+    Cred,
+};
+use serde_json::json;
+
+use crate::error::{SrError, SrResult};
+
+#[cfg(not(test))]
+const RECORD_LOCATION: &str = env!("RAR_RECORD_STORAGE");
+#[cfg(test)]
+const RECORD_LOCATION: &str = "target/sessions";
+
+const ASCIICAST_EXTENSION: &str = "cast";
+const TTYREC_EXTENSION: &str = "ttyrec";
+
+pub struct Recorder<W: Write = File> {
+    format: SRecord,
+    writer: W,
+    start: Instant,
+    /// Incomplete UTF-8 sequence kept for the next asciicast event
+    pending: Vec<u8>,
+}
+
+fn extension(format: SRecord) -> &'static str {
+    match format {
+        SRecord::Ttyrec => TTYREC_EXTENSION,
+        _ => ASCIICAST_EXTENSION,
+    }
+}
+
+/// Creates the storage directory and its missing parents, owned by root with 0700 mode
+fn prepare_storage(dir: &Path) -> std::io::Result<()> {
+    let created: Vec<PathBuf> = dir
+        .ancestors()
+        .take_while(|p| !p.as_os_str().is_empty() && !p.exists())
+        .map(Path::to_path_buf)
+        .collect();
+    create_dir_all_with_privileges(dir)?;
+    with_privileges(&[Cap::CHOWN, Cap::FOWNER], || {
+        for path in created
+            .iter()
+            .rev()
+            .chain(std::iter::once(&dir.to_path_buf()))
+        {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o700))?;
+            std::os::unix::fs::chown(path, Some(0), Some(0))?;
+        }
+        Ok(())
+    })
+}
+
+impl Recorder {
+    /// Opens a new session file, must be called before dropping privileges
+    pub fn create(
+        format: SRecord,
+        user: &Cred,
+        command: &Path,
+        args: &[String],
+        size: (u16, u16),
+    ) -> SrResult<Self> {
+        let dir = Path::new(RECORD_LOCATION);
+        prepare_storage(dir).inspect_err(|e| {
+            error!("Unable to prepare session storage {}: {}", dir.display(), e);
+        })?;
+        let id = format!(
+            "{}-{}-{}",
+            user.user.name,
+            now().as_secs(),
+            nix::unistd::getpid()
+        );
+        let path = dir.join(&id).with_extension(extension(format));
+        let file = with_privileges(&[Cap::DAC_OVERRIDE, Cap::CHOWN], || {
+            let file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&path)?;
+            fchown(&file, Some(0), Some(0))?;
+            Ok(file)
+        })
+        .inspect_err(|e| {
+            error!("Unable to create session file {}: {}", path.display(), e);
+        })?;
+        info!("Recording session {} to {}", id, path.display());
+        let mut recorder = Recorder::new(format, file);
+        recorder.header(command, args, size)?;
+        Ok(recorder)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(format: SRecord, writer: W) -> Self {
+        Recorder {
+            format,
+            writer,
+            start: Instant::now(),
+            pending: Vec::new(),
+        }
+    }
+
+    fn header(&mut self, command: &Path, args: &[String], size: (u16, u16)) -> std::io::Result<()> {
+        if self.format.is_asciicast() {
+            let command = std::iter::once(command.display().to_string())
+                .chain(args.iter().cloned())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let header = json!({
+                "version": 2,
+                "width": size.1,
+                "height": size.0,
+                "timestamp": now().as_secs(),
+                "command": command,
+            });
+            writeln!(self.writer, "{}", header)?;
+        }
+        Ok(())
+    }
+
+    /// Appends a chunk of output to the session
+    pub fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        match self.format {
+            SRecord::Disabled => Ok(()),
+            SRecord::Asciicast => {
+                self.pending.extend_from_slice(data);
+                let valid = match std::str::from_utf8(&self.pending) {
+                    Ok(_) => self.pending.len(),
+                    Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                    Err(_) => self.pending.len(),
+                };
+                let chunk: Vec<u8> = self.pending.drain(..valid).collect();
+                self.event(&chunk)
+            }
+            SRecord::Ttyrec => {
+                let time = now();
+                self.writer
+                    .write_all(&(time.as_secs() as u32).to_le_bytes())?;
+                self.writer.write_all(&time.subsec_micros().to_le_bytes())?;
+                self.writer.write_all(&(data.len() as u32).to_le_bytes())?;
+                self.writer.write_all(data)?;
+                self.writer.flush()
+            }
+        }
+    }
+
+    fn event(&mut self, chunk: &[u8]) -> std::io::Result<()> {
+        if chunk.is_empty() {
+            return Ok(());
+        }
+        let event = json!([
+            self.start.elapsed().as_secs_f64(),
+            "o",
+            String::from_utf8_lossy(chunk)
+        ]);
+        writeln!(self.writer, "{}", event)?;
+        self.writer.flush()
+    }
+
+    /// Flushes the remaining output
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        let pending = std::mem::take(&mut self.pending);
+        self.event(&pending)?;
+        self.writer.flush()
+    }
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Session ids are file names in the storage directory
+fn session_path(id: &str) -> SrResult<(SRecord, PathBuf)> {
+    if id.is_empty() || id.contains('/') || id.starts_with('.') {
+        error!("Invalid session id: {}", id);
+        return Err(SrError::InvalidAgruments);
+    }
+    let path = Path::new(RECORD_LOCATION).join(id);
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ASCIICAST_EXTENSION) => return Ok((SRecord::Asciicast, path)),
+        Some(TTYREC_EXTENSION) => return Ok((SRecord::Ttyrec, path)),
+        _ => {}
+    }
+    [SRecord::Asciicast, SRecord::Ttyrec]
+        .into_iter()
+        .map(|format| {
+            (
+                format,
+                Path::new(RECORD_LOCATION).join(format!("{}.{}", id, extension(format))),
+            )
+        })
+        // the storage directory is not searchable for non root users
+        .find_map(|(format, path)| match path.try_exists() {
+            Ok(true) => Some(Ok((format, path))),
+            Ok(false) => None,
+            Err(e) => Some(Err(e.into())),
+        })
+        .unwrap_or(Err(SrError::ExecutionFailed))
+}
+
+/// Replays a recorded session on stdout, `dump` prints it at once without timing.
+/// The session file is opened without privileges, only its owner (root) can read it.
+pub fn replay(id: &str, dump: bool) -> SrResult<()> {
+    let (format, path) = session_path(id)?;
+    debug!("Replaying session {}", path.display());
+    let file = File::open(&path).inspect_err(|e| {
+        error!("Unable to open session {}: {}", id, e);
+    })?;
+    play(format, BufReader::new(file), std::io::stdout().lock(), dump)?;
+    Ok(())
+}
+
+pub fn play<R: BufRead, W: Write>(
+    format: SRecord,
+    mut reader: R,
+    mut writer: W,
+    dump: bool,
+) -> std::io::Result<()> {
+    let invalid = |e| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+    let mut last: Option<f64> = None;
+    let mut wait = |time: f64| {
+        if let (false, Some(last)) = (dump, last) {
+            if time > last {
+                sleep(Duration::from_secs_f64(time - last));
+            }
+        }
+        last = Some(time);
+    };
+    match format {
+        SRecord::Disabled => {}
+        SRecord::Asciicast => {
+            let mut lines = reader.lines();
+            let header = lines.next().transpose()?.unwrap_or_default();
+            serde_json::from_str::<serde_json::Value>(&header).map_err(invalid)?;
+            for line in lines {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let (time, kind, data): (f64, String, String) =
+                    serde_json::from_str(&line).map_err(invalid)?;
+                if kind != "o" {
+                    continue;
+                }
+                wait(time);
+                writer.write_all(data.as_bytes())?;
+                writer.flush()?;
+            }
+        }
+        SRecord::Ttyrec => {
+            let mut header = [0u8; 12];
+            loop {
+                match reader.read_exact(&mut header) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                    Err(e) => return Err(e),
+                }
+                let field = |i: usize| u32::from_le_bytes(header[i..i + 4].try_into().unwrap());
+                let mut data = vec![0u8; field(8) as usize];
+                reader.read_exact(&mut data)?;
+                wait(field(0) as f64 + field(4) as f64 / 1_000_000.0);
+                writer.write_all(&data)?;
+                writer.flush()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(format: SRecord) -> Vec<u8> {
+        let mut recorder = Recorder::new(format, Vec::new());
+        recorder
+            .header(Path::new("/usr/bin/ls"), &["-l".to_string()], (24, 80))
+            .unwrap();
+        recorder.write(b"hello ").unwrap();
+        // split multi-byte character
+        recorder.write(&"é".as_bytes()[..1]).unwrap();
+        recorder.write(&"é".as_bytes()[1..]).unwrap();
+        recorder.write(b"\r\n").unwrap();
+        recorder.finish().unwrap();
+        let mut output = Vec::new();
+        play(format, recorder.writer.as_slice(), &mut output, true).unwrap();
+        output
+    }
+
+    #[test]
+    fn test_asciicast_roundtrip() {
+        assert_eq!(roundtrip(SRecord::Asciicast), "hello é\r\n".as_bytes());
+        let mut recorder = Recorder::new(SRecord::Asciicast, Vec::new());
+        recorder
+            .header(Path::new("/bin/sh"), &[], (24, 80))
+            .unwrap();
+        let header: serde_json::Value =
+            serde_json::from_slice(recorder.writer.split(|b| *b == b'\n').next().unwrap()).unwrap();
+        assert_eq!(header["version"], 2);
+        assert_eq!(header["width"], 80);
+        assert_eq!(header["command"], "/bin/sh");
+    }
+
+    #[test]
+    fn test_ttyrec_roundtrip() {
+        assert_eq!(roundtrip(SRecord::Ttyrec), "hello é\r\n".as_bytes());
+    }
+
+    #[test]
+    fn test_session_path() {
+        assert_eq!(
+            session_path("../etc/shadow"),
+            Err(SrError::InvalidAgruments)
+        );
+        assert_eq!(session_path(".hidden"), Err(SrError::InvalidAgruments));
+        assert_eq!(session_path(""), Err(SrError::InvalidAgruments));
+        assert_eq!(
+            session_path("root-1-2.ttyrec"),
+            Ok((
+                SRecord::Ttyrec,
+                Path::new(RECORD_LOCATION).join("root-1-2.ttyrec")
+            ))
+        );
+        assert_eq!(session_path("unknown"), Err(SrError::ExecutionFailed));
+    }
+}
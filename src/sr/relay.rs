@@ -0,0 +1,427 @@
+//! Relays the user terminal to the pseudo-terminal of the executed command
+//! This module copies stdin to the pty master, and the pty master to stdout (and the session recorder)
+//! It also forwards signals received by dosr to the command, and handles job control.
+//! Without pseudo-terminal, only the signals are forwarded, see `forward_signals`.
+
+use std::{
+    io::{stdin, stdout, Read, Write},
+    os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd},
+    sync::atomic::{AtomicI32, AtomicU64, Ordering},
+};
+
+use log::{debug, warn};
+use nix::{
+    errno::Errno,
+    fcntl::OFlag,
+    poll::{poll, PollFd, PollFlags, PollTimeout},
+    sys::{
+        signal::{kill, killpg, sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal},
+        termios::{
+            cfmakeraw, tcgetattr, tcsetattr, LocalFlags, SetArg, SpecialCharacterIndices, Termios,
+        },
+        wait::{waitid, Id, WaitPidFlag, WaitStatus},
+    },
+    unistd::{getpid, isatty, pipe2, Pid},
+};
+use pty_process::{blocking::Pty, Size};
+
+use crate::record::Recorder;
+
+const BUFFER_SIZE: usize = 4096;
+
+/// Signals relayed to the command, SIGTSTP is relayed as SIGSTOP (see `forward`)
+const FORWARDED_SIGNALS: [Signal; 5] = [
+    Signal::SIGINT,
+    Signal::SIGTERM,
+    Signal::SIGHUP,
+    Signal::SIGTSTP,
+    Signal::SIGCONT,
+];
+const PTY_SIGNALS: [Signal; 7] = [
+    Signal::SIGINT,
+    Signal::SIGTERM,
+    Signal::SIGHUP,
+    Signal::SIGTSTP,
+    Signal::SIGCONT,
+    Signal::SIGWINCH,
+    Signal::SIGCHLD,
+];
+/// Without pseudo-terminal, the command stays in the process group of dosr, job control is left to the shell
+const NO_PTY_SIGNALS: [Signal; 4] = [
+    Signal::SIGINT,
+    Signal::SIGTERM,
+    Signal::SIGHUP,
+    Signal::SIGCHLD,
+];
+
+/// Bitmask of received signals, set by the signal handler
+static PENDING_SIGNALS: AtomicU64 = AtomicU64::new(0);
+/// Bitmask of the received signals sent by a process, not by the kernel (e.g. the terminal)
+static SENT_SIGNALS: AtomicU64 = AtomicU64::new(0);
+/// Write end of the self-pipe waking up the relay loop
+static WAKEUP_FD: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn on_signal(signal: libc::c_int, info: *mut libc::siginfo_t, _: *mut libc::c_void) {
+    // SI_USER, SI_QUEUE, SI_TKILL... are not positive
+    if !info.is_null() && unsafe { (*info).si_code } <= 0 {
+        SENT_SIGNALS.fetch_or(1 << signal, Ordering::SeqCst);
+    }
+    PENDING_SIGNALS.fetch_or(1 << signal, Ordering::SeqCst);
+    let fd = WAKEUP_FD.load(Ordering::SeqCst);
+    if fd >= 0 {
+        // only async-signal-safe calls here
+        unsafe { libc::write(fd, [0u8].as_ptr().cast(), 1) };
+    }
+}
+
+/// Installs the relay signal handlers, previous handlers are restored on drop.
+/// Installed before the command is spawned, so that no signal sent to dosr is lost.
+pub struct SignalHandlers {
+    handled: &'static [Signal],
+    previous: Vec<(Signal, SigAction)>,
+    wakeup: OwnedFd,
+    _wakeup_write: OwnedFd,
+}
+
+impl SignalHandlers {
+    /// Handles the signals of the pty relay, or only the forwarded ones without pseudo-terminal
+    pub fn install(pty: bool) -> nix::Result<Self> {
+        let handled: &'static [Signal] = if pty { &PTY_SIGNALS } else { &NO_PTY_SIGNALS };
+        let (wakeup, wakeup_write) = pipe2(OFlag::O_NONBLOCK | OFlag::O_CLOEXEC)?;
+        WAKEUP_FD.store(wakeup_write.as_raw_fd(), Ordering::SeqCst);
+        PENDING_SIGNALS.store(0, Ordering::SeqCst);
+        SENT_SIGNALS.store(0, Ordering::SeqCst);
+        let mut handlers = SignalHandlers {
+            handled,
+            previous: Vec::with_capacity(handled.len()),
+            wakeup,
+            _wakeup_write: wakeup_write,
+        };
+        let action = SigAction::new(
+            SigHandler::SigAction(on_signal),
+            SaFlags::SA_RESTART | SaFlags::SA_SIGINFO,
+            SigSet::empty(),
+        );
+        for signal in handled {
+            let previous = unsafe { sigaction(*signal, &action)? };
+            handlers.previous.push((*signal, previous));
+        }
+        Ok(handlers)
+    }
+
+    /// Empties the self-pipe and returns the signals received since the last call,
+    /// with whether they were sent by a process
+    fn take_pending(&self) -> Vec<(Signal, bool)> {
+        let mut buf = [0u8; 64];
+        while matches!(nix::unistd::read(self.wakeup.as_fd(), &mut buf), Ok(n) if n > 0) {}
+        let pending = PENDING_SIGNALS.swap(0, Ordering::SeqCst);
+        let sent = SENT_SIGNALS.swap(0, Ordering::SeqCst);
+        self.handled
+            .iter()
+            .filter(|signal| pending & (1 << **signal as i32) != 0)
+            .map(|signal| (*signal, sent & (1 << *signal as i32) != 0))
+            .collect()
+    }
+}
+
+impl Drop for SignalHandlers {
+    fn drop(&mut self) {
+        WAKEUP_FD.store(-1, Ordering::SeqCst);
+        for (signal, previous) in self.previous.drain(..) {
+            if let Err(e) = unsafe { sigaction(signal, &previous) } {
+                warn!("Failed to restore {} handler: {}", signal, e);
+            }
+        }
+    }
+}
+
+/// Puts stdin in raw mode, the original terminal settings are restored on drop
+struct RawMode {
+    termios: Termios,
+}
+
+impl RawMode {
+    /// Returns None when stdin is not a terminal
+    fn enable() -> nix::Result<Option<Self>> {
+        if !isatty(stdin()).unwrap_or(false) {
+            return Ok(None);
+        }
+        let termios = tcgetattr(stdin())?;
+        let mut raw = termios.clone();
+        cfmakeraw(&mut raw);
+        tcsetattr(stdin(), SetArg::TCSANOW, &raw)?;
+        Ok(Some(RawMode { termios }))
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        if let Err(e) = tcsetattr(stdin(), SetArg::TCSAFLUSH, &self.termios) {
+            warn!("Failed to restore terminal settings: {}", e);
+        }
+    }
+}
+
+fn raw_mode() -> Option<RawMode> {
+    RawMode::enable().unwrap_or_else(|e| {
+        warn!("Failed to set terminal in raw mode: {}", e);
+        None
+    })
+}
+
+/// Returns the (rows, cols) size of the terminal behind `fd`
+pub fn window_size<Fd: AsRawFd>(fd: &Fd) -> Option<(u16, u16)> {
+    let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+    if unsafe { libc::ioctl(fd.as_raw_fd(), libc::TIOCGWINSZ, &mut ws) } != 0 {
+        return None;
+    }
+    Some((ws.ws_row, ws.ws_col))
+}
+
+/// Copies the size of the user terminal to the pseudo-terminal,
+/// the kernel then sends SIGWINCH to the foreground process group of the command
+pub fn copy_window_size(pty: &Pty) {
+    if let Some((rows, cols)) = window_size(&stdin()).or_else(|| window_size(&stdout())) {
+        if let Err(e) = pty.resize(Size::new(rows, cols)) {
+            debug!("Failed to resize pty: {}", e);
+        }
+    }
+}
+
+/// Job control signals are sent to the process group led by the command,
+/// which includes the first process of a PID namespace sandbox
+fn signal_command(child: Pid, signal: Signal) -> nix::Result<()> {
+    match signal {
+        Signal::SIGSTOP | Signal::SIGCONT => killpg(child, signal),
+        signal => kill(child, signal),
+    }
+}
+
+/// The suspend character of the pseudo-terminal, when the line discipline turns it into SIGTSTP
+fn suspend_char(pty: &Pty) -> Option<u8> {
+    let termios = tcgetattr(pty.as_fd()).ok()?;
+    let vsusp = termios.control_chars[SpecialCharacterIndices::VSUSP as usize];
+    (termios.local_flags.contains(LocalFlags::ISIG) && vsusp != libc::_POSIX_VDISABLE)
+        .then_some(vsusp)
+}
+
+/// The command leads its own session, so its process group is orphaned
+/// and the kernel discards SIGTSTP sent to it, even the one of a typed suspend character:
+/// stop it with SIGSTOP instead.
+fn forward(child: Pid, signal: Signal) {
+    let signal = match signal {
+        Signal::SIGTSTP => Signal::SIGSTOP,
+        signal => signal,
+    };
+    debug!("Forwarding {} to the command", signal);
+    if let Err(e) = signal_command(child, signal) {
+        debug!("Failed to forward {}: {}", signal, e);
+    }
+}
+
+/// When the command is stopped, dosr restores the terminal and stops as well,
+/// so the calling shell regains control. Once resumed, the command is continued.
+fn suspend(pty: &Pty, child: Pid, raw: &mut Option<RawMode>) {
+    debug!("Command stopped, suspending dosr");
+    let was_raw = raw.take().is_some();
+    if let Err(e) = kill(getpid(), Signal::SIGSTOP) {
+        warn!("Failed to suspend: {}", e);
+    }
+    debug!("dosr resumed, continuing the command");
+    if was_raw {
+        *raw = raw_mode();
+    }
+    copy_window_size(pty);
+    if let Err(e) = signal_command(child, Signal::SIGCONT) {
+        debug!("Failed to continue the command: {}", e);
+    }
+}
+
+fn handle_signals(handlers: &SignalHandlers, pty: &Pty, child: Pid, raw: &mut Option<RawMode>) {
+    for (signal, _) in handlers.take_pending() {
+        match signal {
+            Signal::SIGWINCH => copy_window_size(pty),
+            Signal::SIGCHLD => {
+                // WEXITED is not requested, the exit status is left to the caller
+                if let Ok(WaitStatus::Stopped(_, _)) =
+                    waitid(Id::Pid(child), WaitPidFlag::WSTOPPED | WaitPidFlag::WNOHANG)
+                {
+                    suspend(pty, child, raw);
+                }
+            }
+            signal if FORWARDED_SIGNALS.contains(&signal) => forward(child, signal),
+            _ => {}
+        }
+    }
+}
+
+/// Relays data until the command closes its side of the pseudo-terminal.
+/// stdin is only forwarded when `forward_stdin` is set, i.e. when it is attached to the pty.
+/// The terminal is put in raw mode during the relay and restored before returning.
+pub fn relay(
+    pty: &Pty,
+    child: Pid,
+    handlers: SignalHandlers,
+    forward_stdin: bool,
+    mut recorder: Option<Recorder>,
+) -> std::io::Result<()> {
+    let mut raw = if forward_stdin { raw_mode() } else { None };
+    let stdin = stdin();
+    let mut stdout = stdout();
+    let mut buf = [0u8; BUFFER_SIZE];
+    let mut stdin_open = forward_stdin;
+    loop {
+        handle_signals(&handlers, pty, child, &mut raw);
+        let mut fds = vec![
+            PollFd::new(pty.as_fd(), PollFlags::POLLIN),
+            PollFd::new(handlers.wakeup.as_fd(), PollFlags::POLLIN),
+        ];
+        if stdin_open {
+            fds.push(PollFd::new(stdin.as_fd(), PollFlags::POLLIN));
+        }
+        match poll(&mut fds, PollTimeout::NONE) {
+            Ok(_) => {}
+            Err(Errno::EINTR) => continue,
+            Err(e) => return Err(e.into()),
+        }
+        let pty_events = fds[0].revents().unwrap_or(PollFlags::empty());
+        let stdin_events = fds
+            .get(2)
+            .and_then(|fd| fd.revents())
+            .unwrap_or(PollFlags::empty());
+        if stdin_events.intersects(PollFlags::POLLIN | PollFlags::POLLHUP) {
+            match read_fd(stdin.as_fd(), &mut buf) {
+                Ok(0) => stdin_open = false,
+                Ok(n) => {
+                    let suspend = suspend_char(pty).is_some_and(|c| buf[..n].contains(&c));
+                    (&*pty).write_all(&buf[..n])?;
+                    if suspend {
+                        forward(child, Signal::SIGTSTP);
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if pty_events.intersects(PollFlags::POLLIN | PollFlags::POLLHUP | PollFlags::POLLERR) {
+            match (&*pty).read(&mut buf) {
+                // EIO is returned once every slave fd is closed
+                Ok(0) => break,
+                Err(e) if e.raw_os_error() == Some(libc::EIO) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+                Ok(n) => {
+                    stdout.write_all(&buf[..n])?;
+                    stdout.flush()?;
+                    if let Some(recorder) = recorder.as_mut() {
+                        if let Err(e) = recorder.write(&buf[..n]) {
+                            warn!("Failed to record session: {}", e);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    drop(raw);
+    if let Some(recorder) = recorder.as_mut() {
+        recorder.finish()?;
+    }
+    Ok(())
+}
+
+/// Without pseudo-terminal, forwards the signals sent to dosr until the command exits.
+/// The command is in the process group of dosr, so it already received the signals
+/// of the terminal: only the signals sent by a process (e.g. kill) are forwarded.
+/// The exit status is left to the caller.
+pub fn forward_signals(handlers: SignalHandlers, child: Pid) -> std::io::Result<()> {
+    loop {
+        for (signal, sent) in handlers.take_pending() {
+            if sent && signal != Signal::SIGCHLD {
+                forward(child, signal);
+            }
+        }
+        match waitid(
+            Id::Pid(child),
+            WaitPidFlag::WEXITED | WaitPidFlag::WNOHANG | WaitPidFlag::WNOWAIT,
+        ) {
+            Ok(WaitStatus::StillAlive) => {}
+            Ok(_) => return Ok(()),
+            Err(Errno::EINTR) => continue,
+            Err(e) => return Err(e.into()),
+        }
+        // a signal received since take_pending, as SIGCHLD, wakes up the poll
+        let mut fds = [PollFd::new(handlers.wakeup.as_fd(), PollFlags::POLLIN)];
+        match poll(&mut fds, PollTimeout::NONE) {
+            Ok(_) | Err(Errno::EINTR) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+fn read_fd(fd: BorrowedFd, buf: &mut [u8]) -> std::io::Result<usize> {
+    nix::unistd::read(fd, buf).map_err(std::io::Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// The handlers and the pending signals are process-wide, and a signal raised
+    /// after the handlers of another test are restored would kill the test binary
+    static SIGNALS: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn test_signal_handlers() {
+        let _guard = SIGNALS.lock().unwrap_or_else(|e| e.into_inner());
+        let handlers = SignalHandlers::install(true).unwrap();
+        assert!(handlers.take_pending().is_empty());
+        nix::sys::signal::raise(Signal::SIGWINCH).unwrap();
+        nix::sys::signal::raise(Signal::SIGHUP).unwrap();
+        let mut fds = [PollFd::new(handlers.wakeup.as_fd(), PollFlags::POLLIN)];
+        assert_eq!(poll(&mut fds, PollTimeout::ZERO), Ok(1));
+        assert_eq!(
+            handlers.take_pending(),
+            vec![(Signal::SIGHUP, true), (Signal::SIGWINCH, true)]
+        );
+        assert!(handlers.take_pending().is_empty());
+        drop(handlers);
+        let current = unsafe {
+            sigaction(
+                Signal::SIGWINCH,
+                &SigAction::new(SigHandler::SigDfl, SaFlags::empty(), SigSet::empty()),
+            )
+        }
+        .unwrap();
+        assert_eq!(current.handler(), SigHandler::SigDfl);
+    }
+
+    #[test]
+    fn test_forward_signals() {
+        let _guard = SIGNALS.lock().unwrap_or_else(|e| e.into_inner());
+        let handlers = SignalHandlers::install(false).unwrap();
+        let mut command = std::process::Command::new("sleep")
+            .arg("10")
+            .spawn()
+            .unwrap();
+        kill(getpid(), Signal::SIGTERM).unwrap();
+        let child = Pid::from_raw(command.id() as i32);
+        forward_signals(handlers, child).unwrap();
+        let status = command.wait().unwrap();
+        assert_eq!(
+            std::os::unix::process::ExitStatusExt::signal(&status),
+            Some(libc::SIGTERM)
+        );
+    }
+
+    #[test]
+    fn test_suspend_char() {
+        let pty = Pty::new().unwrap();
+        assert_eq!(suspend_char(&pty), Some(0x1a));
+        let mut termios = tcgetattr(pty.as_fd()).unwrap();
+        termios.local_flags.remove(LocalFlags::ISIG);
+        tcsetattr(pty.as_fd(), SetArg::TCSANOW, &termios).unwrap();
+        assert_eq!(suspend_char(&pty), None);
+    }
+}
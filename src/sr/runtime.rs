@@ -0,0 +1,115 @@
+//! Maximum runtime of the command, from the `max-runtime` option or `dosr --command-timeout`.
+//! A dosr thread waits for the exit of the command on a pidfd, which never designates a recycled pid.
+//! Once the runtime is exceeded, the command gets SIGTERM, then SIGKILL after a grace period.
+
+use std::{
+    os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd},
+    time::{Duration, Instant},
+};
+
+use log::{debug, warn};
+use nix::{
+    errno::Errno,
+    poll::{poll, PollFd, PollFlags, PollTimeout},
+    sys::signal::Signal,
+};
+
+/// Delay between SIGTERM and SIGKILL
+const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+fn pidfd_open(pid: u32) -> std::io::Result<OwnedFd> {
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as i32) })
+}
+
+fn pidfd_send_signal(pidfd: &OwnedFd, signal: Signal) -> std::io::Result<()> {
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_pidfd_send_signal,
+            pidfd.as_raw_fd(),
+            signal as libc::c_int,
+            std::ptr::null::<libc::siginfo_t>(),
+            0,
+        )
+    };
+    if res < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// True when the process exits before the timeout
+fn wait_exit(pidfd: &OwnedFd, timeout: Duration) -> std::io::Result<bool> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(false);
+        }
+        // longer timeouts are waited in several polls
+        let timeout = PollTimeout::try_from(remaining).unwrap_or(PollTimeout::MAX);
+        let mut fds = [PollFd::new(pidfd.as_fd(), PollFlags::POLLIN)];
+        match poll(&mut fds, timeout) {
+            Err(Errno::EINTR) | Ok(0) => continue,
+            Err(e) => return Err(e.into()),
+            Ok(_) => return Ok(true),
+        }
+    }
+}
+
+fn terminate(pidfd: &OwnedFd, command: &str, limit: Duration) -> std::io::Result<()> {
+    if wait_exit(pidfd, limit)? {
+        return Ok(());
+    }
+    warn!(
+        "Command {} exceeded its maximum runtime of {}s, sending SIGTERM",
+        command,
+        limit.as_secs()
+    );
+    pidfd_send_signal(pidfd, Signal::SIGTERM)?;
+    if wait_exit(pidfd, GRACE_PERIOD)? {
+        return Ok(());
+    }
+    warn!(
+        "Command {} still running {}s after SIGTERM, sending SIGKILL",
+        command,
+        GRACE_PERIOD.as_secs()
+    );
+    pidfd_send_signal(pidfd, Signal::SIGKILL)
+}
+
+/// Starts the thread terminating the command after `limit`, it ends with the command.
+/// The command must not be reaped before this call.
+pub fn supervise(pid: u32, limit: Duration, command: String) -> std::io::Result<()> {
+    let pidfd = pidfd_open(pid)?;
+    debug!("Command {} limited to {}s", command, limit.as_secs());
+    std::thread::Builder::new()
+        .name("max-runtime".to_string())
+        .spawn(move || {
+            if let Err(e) = terminate(&pidfd, &command, limit) {
+                warn!("Unable to terminate command {}: {}", command, e);
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait_exit() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("10")
+            .spawn()
+            .unwrap();
+        let pidfd = pidfd_open(child.id()).unwrap();
+        assert!(!wait_exit(&pidfd, Duration::from_millis(50)).unwrap());
+        pidfd_send_signal(&pidfd, Signal::SIGTERM).unwrap();
+        assert!(wait_exit(&pidfd, Duration::from_secs(5)).unwrap());
+        assert!(child.wait().unwrap().code().is_none());
+    }
+}
@@ -0,0 +1,240 @@
+//! Runs the command in fresh namespaces, as set by the `sandbox` option.
+//! Called in the command process before exec, with `CAP_SYS_ADMIN` in the effective set:
+//! the mount namespace is made private, the `readonly` paths are bind mounted read-only,
+//! and `/tmp` is replaced by an empty tmpfs.
+//! With a new PID namespace, the command process forks: the child is PID 1 of the namespace,
+//! with its own `/proc`, and the parent forwards signals and exits with the status of the child.
+//! The parent also follows job control: it stops when the command stops, so that dosr and the shell see it.
+
+use std::{
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    sync::atomic::{AtomicI32, Ordering},
+};
+
+use capctl::CapState;
+use log::debug;
+use nix::{
+    errno::Errno,
+    mount::{mount, MsFlags},
+    sched::{unshare, CloneFlags},
+    sys::{
+        prctl::set_pdeathsig,
+        signal::{raise, signal, SigHandler, Signal},
+        statvfs::{statvfs, FsFlags},
+        wait::{waitpid, WaitPidFlag, WaitStatus},
+    },
+    unistd::{fork, ForkResult, Pid},
+};
+use rar_common::database::options::SSandbox;
+
+/// Signals forwarded to the command by the PID namespace parent
+const FORWARDED_SIGNALS: [Signal; 11] = [
+    Signal::SIGHUP,
+    Signal::SIGINT,
+    Signal::SIGQUIT,
+    Signal::SIGTERM,
+    Signal::SIGUSR1,
+    Signal::SIGUSR2,
+    Signal::SIGWINCH,
+    Signal::SIGCONT,
+    Signal::SIGTSTP,
+    Signal::SIGTTIN,
+    Signal::SIGTTOU,
+];
+
+static COMMAND_PID: AtomicI32 = AtomicI32::new(0);
+
+fn clone_flags(sandbox: &SSandbox) -> CloneFlags {
+    let mut flags = CloneFlags::CLONE_NEWNS;
+    for (enabled, flag) in [
+        (sandbox.pid, CloneFlags::CLONE_NEWPID),
+        (sandbox.ipc, CloneFlags::CLONE_NEWIPC),
+        (sandbox.uts, CloneFlags::CLONE_NEWUTS),
+        (sandbox.net, CloneFlags::CLONE_NEWNET),
+    ] {
+        if enabled {
+            flags |= flag;
+        }
+    }
+    flags
+}
+
+/// A read-only bind remount must keep the locked flags of the mount point
+fn remount_flags(flags: FsFlags) -> MsFlags {
+    let mut ms = MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY;
+    for (fs, flag) in [
+        (FsFlags::ST_NOSUID, MsFlags::MS_NOSUID),
+        (FsFlags::ST_NODEV, MsFlags::MS_NODEV),
+        (FsFlags::ST_NOEXEC, MsFlags::MS_NOEXEC),
+    ] {
+        if flags.contains(fs) {
+            ms |= flag;
+        }
+    }
+    ms
+}
+
+fn remount_readonly(path: &str) -> nix::Result<()> {
+    debug!("Mounting {} read-only", path);
+    match mount(
+        Some(path),
+        path,
+        None::<&str>,
+        MsFlags::MS_BIND | MsFlags::MS_REC,
+        None::<&str>,
+    ) {
+        // nothing to protect
+        Err(Errno::ENOENT) => return Ok(()),
+        result => result?,
+    }
+    let flags = statvfs(path)?.flags();
+    mount(
+        None::<&str>,
+        path,
+        None::<&str>,
+        remount_flags(flags),
+        None::<&str>,
+    )
+}
+
+/// A new network namespace only has a loopback interface, which is down
+fn loopback_up() -> std::io::Result<()> {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+    let mut ifr: libc::ifreq = unsafe { std::mem::zeroed() };
+    for (dst, src) in ifr.ifr_name.iter_mut().zip(b"lo") {
+        *dst = *src as libc::c_char;
+    }
+    let raw = socket.as_raw_fd();
+    if unsafe { libc::ioctl(raw, libc::SIOCGIFFLAGS, &mut ifr) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    unsafe { ifr.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short };
+    if unsafe { libc::ioctl(raw, libc::SIOCSIFFLAGS, &mut ifr) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+extern "C" fn forward_signal(sig: libc::c_int) {
+    let pid = COMMAND_PID.load(Ordering::SeqCst);
+    // the first process of a PID namespace ignores the stop signals of its parent namespace,
+    // unless it handles them: only SIGSTOP is always delivered
+    let sig = match sig {
+        libc::SIGTSTP | libc::SIGTTIN | libc::SIGTTOU => libc::SIGSTOP,
+        sig => sig,
+    };
+    if pid > 0 {
+        unsafe { libc::kill(pid, sig) };
+    }
+}
+
+/// Waits for the command, then exits like it did
+fn wait_command(child: Pid) -> ! {
+    COMMAND_PID.store(child.as_raw(), Ordering::SeqCst);
+    for sig in FORWARDED_SIGNALS {
+        let _ = unsafe { signal(sig, SigHandler::Handler(forward_signal)) };
+    }
+    let code = loop {
+        match waitpid(child, Some(WaitPidFlag::WUNTRACED)) {
+            Ok(WaitStatus::Exited(_, code)) => break code,
+            // SIGCONT is then forwarded to the command
+            Ok(WaitStatus::Stopped(_, _)) => {
+                let _ = raise(Signal::SIGSTOP);
+            }
+            Ok(WaitStatus::Signaled(_, sig, _)) => {
+                let _ = unsafe { signal(sig, SigHandler::SigDfl) };
+                let _ = raise(sig);
+                break 128 + sig as i32;
+            }
+            Err(Errno::EINTR) | Ok(_) => continue,
+            Err(_) => break 1,
+        }
+    };
+    unsafe { libc::_exit(code) }
+}
+
+/// Forks the first process of the PID namespace, the parent never returns
+fn fork_pid_namespace() -> std::io::Result<()> {
+    match unsafe { fork() }? {
+        ForkResult::Child => {
+            // the namespace dies with the parent, even when it is killed at the maximum runtime
+            set_pdeathsig(Signal::SIGKILL)?;
+            mount(
+                Some("proc"),
+                "/proc",
+                Some("proc"),
+                MsFlags::MS_NOSUID | MsFlags::MS_NODEV | MsFlags::MS_NOEXEC,
+                None::<&str>,
+            )?;
+            Ok(())
+        }
+        ForkResult::Parent { child } => {
+            // the parent never execs: it must not keep privileges, nor the exec status pipe of dosr
+            let _ = CapState::empty().set_current();
+            unsafe { libc::close_range(3, libc::c_uint::MAX, 0) };
+            wait_command(child)
+        }
+    }
+}
+
+/// Enters the namespaces of the sandbox, needs `CAP_SYS_ADMIN`, and `CAP_NET_ADMIN` for `net`
+pub fn enter(sandbox: &SSandbox) -> std::io::Result<()> {
+    let flags = clone_flags(sandbox);
+    debug!("unshare {:?}", flags);
+    unshare(flags)?;
+    mount(
+        None::<&str>,
+        "/",
+        None::<&str>,
+        MsFlags::MS_REC | MsFlags::MS_PRIVATE,
+        None::<&str>,
+    )?;
+    for path in &sandbox.readonly {
+        remount_readonly(path)?;
+    }
+    if sandbox.private_tmp {
+        mount(
+            Some("tmpfs"),
+            "/tmp",
+            Some("tmpfs"),
+            MsFlags::MS_NOSUID | MsFlags::MS_NODEV,
+            Some("mode=1777"),
+        )?;
+    }
+    if sandbox.net {
+        loopback_up()?;
+    }
+    if sandbox.pid {
+        fork_pid_namespace()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clone_flags() {
+        assert_eq!(clone_flags(&SSandbox::default()), CloneFlags::CLONE_NEWNS);
+        let sandbox = SSandbox::builder().pid(true).net(true).build();
+        assert_eq!(
+            clone_flags(&sandbox),
+            CloneFlags::CLONE_NEWNS | CloneFlags::CLONE_NEWPID | CloneFlags::CLONE_NEWNET
+        );
+    }
+
+    #[test]
+    fn test_remount_flags() {
+        let base = MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY;
+        assert_eq!(remount_flags(FsFlags::empty()), base);
+        assert_eq!(
+            remount_flags(FsFlags::ST_NOSUID | FsFlags::ST_NODEV | FsFlags::ST_RDONLY),
+            base | MsFlags::MS_NOSUID | MsFlags::MS_NODEV
+        );
+    }
+}
@@ -12,6 +12,7 @@ use nix::{
     libc::dev_t,
     libc::{pid_t, uid_t},
     sys::signal::kill,
+    unistd::User,
 };
 use serde::{Deserialize, Serialize};
 
@@ -203,23 +204,19 @@ fn save_cookies(user: &Cred, cookies: &[CookieVersion]) -> Result<(), Box<dyn Er
 }
 fn find_valid_cookie(
     from: &Cred,
-    cred_asked: &Cred,
+    asked: &User,
     constraint: &STimeout,
     editcookie: fn(&mut CookieVersion),
 ) -> Option<CookieVersion> {
     let mut cookies = read_cookies(from).unwrap_or_default();
     let mut to_remove = Vec::new();
     let mut res = None;
-    debug!(
-        "Constraints for {} : {:?}",
-        cred_asked.user.uid.as_raw(),
-        constraint
-    );
+    debug!("Constraints for {} : {:?}", asked.uid.as_raw(), constraint);
     for (a, cookiev) in cookies.iter_mut().enumerate() {
         match cookiev {
             CookieVersion::V1(cookie) => {
                 debug!("Checking cookie: {:?}", cookie);
-                if cookie.auth_uid != cred_asked.user.uid.as_raw()
+                if cookie.auth_uid != asked.uid.as_raw()
                     || cookie.timestamp_type != constraint.type_field.unwrap_or_default()
                 {
                     continue;
@@ -251,11 +248,11 @@ fn find_valid_cookie(
 
 /// Check if the credentials are valid
 /// @param from: the credentials of the user that want to execute a command
-/// @param cred_asked: the credentials of the user that is asked to execute a command
+/// @param asked: the user whose password was asked
 /// @param max_offset: the maximum offset between the current time and the time of the credentials, including the type of the offset
 /// @return true if the credentials are valid, false otherwise
-pub(crate) fn is_valid(from: &Cred, cred_asked: &Cred, constraint: &STimeout) -> bool {
-    find_valid_cookie(from, cred_asked, constraint, |_c| {
+pub(crate) fn is_valid(from: &Cred, asked: &User, constraint: &STimeout) -> bool {
+    find_valid_cookie(from, asked, constraint, |_c| {
         debug!("Found valid cookie ");
     })
     .is_some()
@@ -264,10 +261,10 @@ pub(crate) fn is_valid(from: &Cred, cred_asked: &Cred, constraint: &STimeout) ->
 /// Add a cookie to the user's cookie file
 pub(crate) fn update_cookie(
     from: &Cred,
-    cred_asked: &Cred,
+    asked: &User,
     constraint: &STimeout,
 ) -> Result<(), Box<dyn Error>> {
-    let res = find_valid_cookie(from, cred_asked, constraint, |cookie| match cookie {
+    let res = find_valid_cookie(from, asked, constraint, |cookie| match cookie {
         CookieVersion::V1(cookie) => {
             cookie.usage += 1;
             cookie.timestamp = Utc::now().timestamp();
@@ -278,7 +275,7 @@ pub(crate) fn update_cookie(
         let mut cookies = read_cookies(from).unwrap_or_default();
         let parent_record = ParentRecord::new(&constraint.type_field.unwrap_or_default(), from);
         let cookie = CookieVersion::V1(Cookiev1 {
-            auth_uid: cred_asked.user.uid.as_raw(),
+            auth_uid: asked.uid.as_raw(),
             timestamp_type: constraint.type_field.unwrap_or_default(),
             start_time: Utc::now().timestamp(),
             timestamp: Utc::now().timestamp(),
@@ -331,10 +328,10 @@ mod test {
             max_usage: Some(1),
             _extra_fields: Default::default(),
         };
-        assert!(!is_valid(&cred, &cred, &constraint));
-        assert!(update_cookie(&cred, &cred, &constraint).is_ok());
-        assert!(is_valid(&cred, &cred, &constraint));
-        assert!(update_cookie(&cred, &cred, &constraint).is_ok());
-        assert!(!is_valid(&cred, &cred, &constraint));
+        assert!(!is_valid(&cred, &cred.user, &constraint));
+        assert!(update_cookie(&cred, &cred.user, &constraint).is_ok());
+        assert!(is_valid(&cred, &cred.user, &constraint));
+        assert!(update_cookie(&cred, &cred.user, &constraint).is_ok());
+        assert!(!is_valid(&cred, &cred.user, &constraint));
     }
 }